@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(response) = std::str::from_utf8(data) {
+        // errors are fine, panics are not
+        let _ = api::api::binance::withdrawals_deserialize(response);
+    }
+});
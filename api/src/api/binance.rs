@@ -1,21 +1,28 @@
 use hmac::{Hmac, Mac};
-use log::{debug, info};
+use log::{debug, info, warn};
 use polars::prelude::*;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use std::collections::HashMap;
+use sha2::Digest;
+use std::collections::{HashMap, HashSet};
 
 //
 
 use crate::structs::APIClient;
+use crate::structs::AccountConfig;
 use crate::structs::AppConfig;
+use crate::structs::Secret;
+use crate::structs::Sweep;
 
 //
 
+#[derive(Clone)]
 pub struct API<'a> {
     pub label: &'a str,
-    pub client: APIClient<'a>,
+    pub client: APIClient,
     pub config_app: &'a AppConfig,
     pub pairs: HashMap<String, crate::structs::Pair>,
+    pub account: AccountConfig,
+    secret: Secret,
 }
 
 //
@@ -48,29 +55,61 @@ struct Pair {
 
 impl API<'_> {
     pub fn new(config: &AppConfig) -> Result<API, Box<dyn std::error::Error>> {
+        let account = crate::api::accounts_get("binance", config)?
+            .into_iter()
+            .next()
+            .ok_or("binance account not found")?;
+        let mut api = API::new_account(config, account)?;
+        api.pairs_get()?;
+
+        Ok(api)
+    }
+
+    //
+
+    /// One API per account, the pairs are requested once and shared.
+    pub fn new_accounts(config: &AppConfig) -> Result<Vec<API<'_>>, Box<dyn std::error::Error>> {
+        let mut apis: Vec<API> = Vec::new();
+        for account in crate::api::accounts_get("binance", config)? {
+            let mut api = API::new_account(config, account)?;
+            match apis.first() {
+                Some(x) => api.pairs = x.pairs.clone(),
+                None => api.pairs_get()?,
+            }
+            apis.push(api);
+        }
+
+        Ok(apis)
+    }
+
+    //
+
+    pub fn new_account(
+        config: &AppConfig,
+        account: AccountConfig,
+    ) -> Result<API<'_>, Box<dyn std::error::Error>> {
+        let key = crate::secrets::secret_get(&account.key)?;
+        let mut key = HeaderValue::from_str(key.expose())?;
+        key.set_sensitive(true);
         let mut headers = HeaderMap::new();
-        headers.insert(
-            HeaderName::from_static("x-mbx-apikey"),
-            HeaderValue::from_str(&std::env::var("BINANCE_API_KEY")?)?,
-        );
+        headers.insert(HeaderName::from_static("x-mbx-apikey"), key);
 
         let label = "binance";
-        let mut api = API {
+        Ok(API {
             label,
-            client: crate::api::client_get(label, config, headers)?,
+            client: crate::api::client_get(label, &account.name, config, headers)?,
             config_app: config,
             pairs: HashMap::new(),
-        };
-        api.pairs_get()?;
-
-        Ok(api)
+            secret: crate::secrets::secret_get(&account.secret)?,
+            account,
+        })
     }
 
     //
 
     pub fn pairs_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let url = format!("{}/api/v3/exchangeInfo", self.client.url);
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
         let response_json: serde_json::Value = serde_json::from_str(&response)?;
         let response_data: Vec<serde_json::Value> =
             serde_json::from_value(response_json["symbols"].clone())?;
@@ -88,7 +127,11 @@ impl API<'_> {
             })
             .collect::<HashMap<String, serde_json::Value>>();
         info!("number of pairs: {}", pairs_data.len());
-        crate::config_write_json(&pairs_data, &crate::paths::file_pairs_binance())?;
+        crate::config_write_json(
+            &pairs_data,
+            &crate::paths::file_pairs_binance(),
+            self.config_app,
+        )?;
 
         let pairs_binance = pairs_data
             .into_iter()
@@ -97,56 +140,7 @@ impl API<'_> {
         let mut pairs = HashMap::new();
 
         for (pair_binance_label, pair_binance_data) in pairs_binance {
-            let mut pair_data = crate::structs::Pair {
-                spot: pair_binance_data.spot,
-                status: pair_binance_data.status,
-                target: pair_binance_data.target,
-                target_precision: pair_binance_data.target_precision,
-                quote: pair_binance_data.quote,
-                quote_precision: pair_binance_data.quote_precision,
-                ..Default::default()
-            };
-
-            for filter in pair_binance_data.filters.iter() {
-                match filter
-                    .get("filterType")
-                    .ok_or("filter type not found")?
-                    .as_str()
-                    .ok_or("str not found")?
-                {
-                    "LOT_SIZE" => {
-                        pair_data.filter_quantity_min = filter
-                            .get("minQty")
-                            .ok_or("min quantity not found")?
-                            .as_str()
-                            .ok_or("minqty as_str failed")?
-                            .parse::<f64>()?;
-                        pair_data.filter_quantity_max = filter
-                            .get("maxQty")
-                            .ok_or("max quantity not found")?
-                            .as_str()
-                            .ok_or("maxqty as_str failed")?
-                            .parse::<f64>()?;
-                        pair_data.filter_step_size = filter
-                            .get("stepSize")
-                            .ok_or("step size not found")?
-                            .as_str()
-                            .ok_or("stepsize as_str failed")?
-                            .parse::<f64>()?;
-                    }
-                    "NOTIONAL" => {
-                        pair_data.filter_notional_min = filter
-                            .get("minNotional")
-                            .ok_or("minnotional not found")?
-                            .as_str()
-                            .ok_or("minnotional as_str failed")?
-                            .parse::<f64>()?
-                    }
-                    _ => {}
-                }
-            }
-
-            pairs.insert(pair_binance_label, pair_data);
+            pairs.insert(pair_binance_label, pair_convert(pair_binance_data)?);
         }
         info!("number of pairs: {}", pairs.len());
         self.pairs = pairs;
@@ -156,72 +150,169 @@ impl API<'_> {
 
     //
 
+    /// Symbols no longer listed, from archived snapshots and the configured list.
+    fn pairs_delisted_get(
+        &self,
+    ) -> Result<HashMap<String, crate::structs::Pair>, Box<dyn std::error::Error>> {
+        let mut pairs = HashMap::new();
+        for (k, v) in crate::universe::entries_delisted(&format!("pairs-{}", self.label))? {
+            if !self.pairs.contains_key(&k) {
+                pairs.insert(k, pair_convert(serde_json::from_value(v)?)?);
+            }
+        }
+        // nothing is known about these but their name
+        for symbol in self.config_app.history.symbols_delisted.iter() {
+            if self.pairs.contains_key(symbol) | pairs.contains_key(symbol) {
+                continue;
+            }
+            let quote = self
+                .config_app
+                .history
+                .quotes
+                .iter()
+                .find(|x| symbol.ends_with(x.as_str()))
+                .cloned()
+                .unwrap_or_default();
+            pairs.insert(
+                symbol.clone(),
+                crate::structs::Pair {
+                    spot: true,
+                    status: String::from("DELISTED"),
+                    target: symbol.trim_end_matches(&quote).to_string(),
+                    quote,
+                    ..Default::default()
+                },
+            );
+        }
+
+        Ok(pairs)
+    }
+
+    //
+
     pub fn history_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         info!("{} history started", self.label);
+        // binance serves klines up to 1M, longer intervals are resampled from stored klines
+        if crate::interval_parse(&self.config_app.history.interval)?.1 == 'y' {
+            return Err(format!(
+                "interval {} is not served by binance, resample 1M klines instead",
+                self.config_app.history.interval
+            )
+            .into());
+        }
 
-        let dir_path = crate::paths::dir_klines().join(self.label);
+        let dir_path =
+            crate::paths::dir_klines_interval(self.label, &self.config_app.history.interval);
         debug!(
             "{} history target directory: {}",
             self.label,
             &dir_path.as_path().display()
         );
-        crate::paths::dir_create(&dir_path);
+
+        // klines were stored without the interval level before
+        for dir_entry in crate::paths::dir_list(&crate::paths::dir_klines().join(self.label))? {
+            if dir_entry.path.is_file() & (dir_entry.extension == "feather") {
+                info!(
+                    "moving {} to {}",
+                    dir_entry.stem,
+                    &dir_path.as_path().display()
+                );
+                std::fs::rename(
+                    &dir_entry.path,
+                    dir_path.join(format!("{}.feather", dir_entry.stem)),
+                )?;
+            }
+        }
 
         let fiat: HashMap<String, serde_json::Value> =
             crate::json_read(&crate::paths::file_fiat())?;
         let stablecoins: HashMap<String, serde_json::Value> =
             crate::json_read(&&crate::paths::file_stablecoins())?;
 
-        let pairs = self
+        let config_app = self.config_app;
+        let history = &config_app.history;
+        let selected = |v: &crate::structs::Pair| {
+            (!history.spot_only | v.spot)
+                & (!history.quote_only | history.quotes.contains(&v.quote))
+                & (!history.fiat_removed | !fiat.contains_key(&v.target))
+                & (!history.stablecoins_removed | !stablecoins.contains_key(&v.target))
+        };
+        let mut pairs = self
             .pairs
             .iter()
-            .filter(|(_, v)| {
-                if self.config_app.history.spot_only {
-                    v.spot
-                } else {
-                    true
-                }
-            })
-            .filter(|(_, v)| {
-                if self.config_app.history.quote_only {
-                    self.config_app.history.quotes.contains(&v.quote)
-                } else {
-                    true
-                }
-            })
-            .filter(|(_, v)| {
-                if self.config_app.history.tradable_only {
-                    v.status == "TRADING"
-                } else {
-                    true
-                }
-            })
-            .filter(|(_, v)| {
-                if self.config_app.history.fiat_removed {
-                    !fiat.contains_key(&v.target)
-                } else {
-                    true
-                }
-            })
-            .filter(|(_, v)| {
-                if self.config_app.history.stablecoins_removed {
-                    !stablecoins.contains_key(&v.target)
-                } else {
-                    true
-                }
-            })
+            .filter(|(_, v)| !history.tradable_only | (v.status == "TRADING"))
+            .filter(|(_, v)| selected(v))
             .map(|(k, _)| k.clone())
             .collect::<Vec<String>>();
+        // requests go out in the same order on every run
+        pairs.sort();
+        // the tickers of all pairs weigh 80, they are only requested for a ranking rule
+        if history.ranking.top_n_by_volume.is_some() | (history.ranking.min_quote_volume > 0.0) {
+            let tickers = self.tickers_get()?;
+            pairs = crate::pairs_rank(
+                self.label,
+                &history.interval,
+                &history.ranking,
+                &pairs,
+                &tickers,
+            )?;
+        }
 
-        let n_pairs = pairs.len();
+        let mut pairs_delisted = if history.delisted {
+            self.pairs_delisted_get()?
+                .into_iter()
+                .filter(|(_, v)| selected(v))
+                .map(|(k, _)| k)
+                .collect::<Vec<String>>()
+        } else {
+            Vec::new()
+        };
+        pairs_delisted.sort();
+        // a delisted symbol is downloaded once more after the end of its last trading period
+        let universe = crate::universe::universe_get(self.label, &history.interval)?;
+        pairs_delisted.retain(|pair| {
+            let ts_written = dir_path
+                .join(format!("{pair}.feather"))
+                .metadata()
+                .and_then(|x| x.modified())
+                .ok()
+                .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|x| x.as_millis() as i64);
+            match (ts_written, universe.get(pair).and_then(|x| x.last())) {
+                (None, _) => true,
+                (Some(ts), Some(period)) => ts < period.1,
+                (Some(_), None) => false,
+            }
+        });
+
+        let n_pairs = pairs.len() + pairs_delisted.len();
         debug!("number of pairs to get history for: {}", n_pairs);
-        for (index, pair) in pairs.iter().enumerate() {
+        let concurrency = self.client.concurrency;
+        crate::api::pairs_run(self, concurrency, &pairs, |api, index, pair| {
             info!("{} / {} - {}", index + 1, n_pairs, pair);
-            self.pair_history_get(&pair)?;
-        }
+            api.pair_history_get(pair)
+        })?;
+        // klines of delisted symbols may no longer be served
+        crate::api::pairs_run(self, concurrency, &pairs_delisted, |api, index, pair| {
+            info!(
+                "{} / {} - {} (delisted)",
+                pairs.len() + index + 1,
+                n_pairs,
+                pair
+            );
+            if let Err(e) = api.pair_history_get(pair) {
+                warn!("{} history failed: {}", pair, e);
+            }
+            Ok(())
+        })?;
 
         info!("Mature pairs");
-        crate::pairs_mature(self.label)?;
+        crate::pairs_mature(
+            self.label,
+            &self.config_app.history.interval,
+            &self.config_app.history.mature,
+        )?;
+        crate::universe::universe_write(self.label, &self.config_app.history.interval)?;
         info!("History finished");
 
         Ok(())
@@ -230,15 +321,15 @@ impl API<'_> {
     //
 
     fn pair_history_get(&mut self, pair: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = crate::paths::dir_klines()
-            .join(self.label)
-            .join(format!("{pair}.feather"));
+        let file_path =
+            crate::paths::dir_klines_interval(self.label, &self.config_app.history.interval)
+                .join(format!("{pair}.feather"));
         debug!("{} file_path is {}", pair, file_path.as_path().display());
 
         let lf = if file_path.is_file() {
             info!("previous data exists for {}", pair);
             let lf_old = crate::feather_read(&file_path)?;
-            let ts_last_available = crate::column_maxu(lf_old.clone(), "ts")? as u32;
+            let ts_last_available = crate::column_maxi(lf_old.clone(), "ts")?;
             debug!("last available for {} is {}", pair, ts_last_available);
             let lf_new = self.klines_history_get(pair, ts_last_available)?.select([
                 col("ts"),
@@ -267,6 +358,11 @@ impl API<'_> {
                 col("number of trades"),
             ])
         };
+        let lf = if self.config_app.history.do_backfill {
+            self.klines_backfill_get(pair, lf)?
+        } else {
+            lf
+        };
         let mut df = crate::timestamps_missing(lf, &self.config_app.history.interval)?
             .sort("ts", Default::default())
             .collect()?;
@@ -279,22 +375,143 @@ impl API<'_> {
 
     //
 
+    fn klines_backfill_get(
+        &mut self,
+        pair: &str,
+        lf: LazyFrame,
+    ) -> Result<LazyFrame, Box<dyn std::error::Error>> {
+        let dir_path = crate::paths::dir_gaps()
+            .join(self.label)
+            .join(&self.config_app.history.interval);
+        crate::paths::dir_create(&dir_path);
+        let file_path = dir_path.join(format!("{pair}.json"));
+
+        let gaps_recorded: Vec<crate::structs::KlinesGap> = if file_path.is_file() {
+            crate::json_read(&file_path)?
+        } else {
+            Vec::new()
+        };
+        let gaps = crate::timestamps_gaps(lf.clone(), &self.config_app.history.interval)?;
+        let confirmed = |ts_start: i64, ts_end: i64| {
+            gaps_recorded.iter().any(|x| {
+                (x.status == crate::structs::GapStatus::Confirmed)
+                    & (x.ts_start <= ts_start)
+                    & (ts_end <= x.ts_end)
+            })
+        };
+        // the gap file holds the current gaps only, filled ones are dropped
+        let mut gaps_current = gaps_recorded
+            .iter()
+            .filter(|x| {
+                gaps.iter()
+                    .any(|(ts_start, ts_end)| (x.ts_start <= *ts_end) & (*ts_start <= x.ts_end))
+            })
+            .cloned()
+            .collect::<Vec<crate::structs::KlinesGap>>();
+        let gaps = gaps
+            .into_iter()
+            .filter(|(ts_start, ts_end)| !confirmed(*ts_start, *ts_end))
+            .collect::<Vec<(i64, i64)>>();
+        if !gaps.is_empty() {
+            info!("{} gaps to backfill for {}", gaps.len(), pair);
+        }
+
+        let interval = &self.config_app.history.interval;
+        let mut lfs = Vec::new();
+        for (ts_start, ts_end) in gaps {
+            let mut tss_fetched = HashSet::new();
+            let mut ts = ts_start;
+            while ts <= ts_end {
+                let chunk = self.klines_get(pair, Some(1000), Some(ts), Some(ts_end))?;
+                if chunk.height() == 0 {
+                    break;
+                }
+                tss_fetched.extend(crate::column_values_i64(chunk.clone().lazy(), "ts")?);
+                ts = crate::interval_add_ms(
+                    crate::column_maxi(chunk.clone().lazy(), "ts")?,
+                    interval,
+                    1,
+                )?;
+                lfs.push(chunk.lazy());
+            }
+
+            let recorded_at = crate::utc_ms()?;
+            let mut ranges: Vec<crate::structs::KlinesGap> = Vec::new();
+            let (mut ts, mut n) = (ts_start, 0);
+            while ts <= ts_end {
+                let status = if tss_fetched.contains(&ts) {
+                    crate::structs::GapStatus::Fixed
+                } else {
+                    crate::structs::GapStatus::Confirmed
+                };
+                match ranges.last_mut() {
+                    Some(x) if x.status == status => x.ts_end = ts,
+                    _ => ranges.push(crate::structs::KlinesGap {
+                        ts_start: ts,
+                        ts_end: ts,
+                        status,
+                        recorded_at,
+                    }),
+                }
+                n += 1;
+                ts = crate::interval_add_ms(ts_start, interval, n)?;
+            }
+            for x in ranges.iter() {
+                info!(
+                    "{} gap {} - {} is {:?}",
+                    pair,
+                    crate::unix_ms_to_time(x.ts_start)?,
+                    crate::unix_ms_to_time(x.ts_end)?,
+                    x.status
+                );
+            }
+            gaps_current.extend(
+                ranges
+                    .into_iter()
+                    .filter(|x| x.status == crate::structs::GapStatus::Confirmed),
+            );
+        }
+        gaps_current.sort_by_key(|x| x.ts_start);
+        if file_path.is_file() | !gaps_current.is_empty() {
+            crate::json_write(&gaps_current, &file_path)?;
+        }
+
+        if lfs.is_empty() {
+            return Ok(lf);
+        }
+        // fetched rows go first so they replace the null rows of the stored gaps
+        let lf_fetched = concat(lfs, true, true)?.select([
+            col("ts"),
+            col("open"),
+            col("high"),
+            col("low"),
+            col("close"),
+            col("quote asset volume"),
+            col("number of trades"),
+        ]);
+        Ok(concat([lf_fetched, lf], true, true)?
+            .unique_stable(
+                Some(Vec::from(["ts".to_string()])),
+                UniqueKeepStrategy::First,
+            )
+            .sort("ts", Default::default()))
+    }
+
+    //
+
     fn klines_history_get(
         &mut self,
         pair: &str,
-        ts_last_available: u32,
+        ts_last_available: i64,
     ) -> Result<LazyFrame, Box<dyn std::error::Error>> {
-        let mut lf = self.klines_get(pair, Some(1000), None)?.lazy();
+        let mut lf = self.klines_get(pair, Some(1000), None, None)?.lazy();
 
         let mut df = lf.clone().select([col("ts")]).collect()?;
 
-        let mut tss = df
-            .column("ts")?
-            .u32()?
-            .clone()
-            .into_iter()
-            .map(|x| Ok(x.ok_or("ts not found")?))
-            .collect::<Result<Vec<u32>, Box<dyn std::error::Error>>>()?;
+        let mut tss = crate::column_values_i64(df.clone().lazy(), "ts")?;
+        if tss.is_empty() {
+            return Err(format!("{} klines not found", pair).into());
+        }
 
         let mut ts = tss[0];
 
@@ -302,24 +519,16 @@ impl API<'_> {
             "{} {} from {}, nrows {}",
             pair,
             self.config_app.history.interval,
-            crate::unix_s_to_time(ts)?,
+            crate::unix_ms_to_time(ts)?,
             df.shape().0
         );
 
         while (df.shape().0 > 1) & !tss.contains(&ts_last_available) {
-            let chunk = self
-                .klines_get(pair, Some(1000), Some(ts as u64 * 1000))?
-                .lazy();
+            let chunk = self.klines_get(pair, Some(1000), None, Some(ts))?.lazy();
 
             df = chunk.clone().select([col("ts")]).collect()?;
 
-            tss = df
-                .column("ts")?
-                .u32()?
-                .clone()
-                .into_iter()
-                .map(|x| Ok(x.ok_or("ts not found")?))
-                .collect::<Result<Vec<u32>, Box<dyn std::error::Error>>>()?;
+            tss = crate::column_values_i64(df.clone().lazy(), "ts")?;
 
             ts = tss[0];
 
@@ -327,7 +536,7 @@ impl API<'_> {
                 "{} {} from {}, nrows {}",
                 pair,
                 self.config_app.history.interval,
-                crate::unix_s_to_time(ts)?,
+                crate::unix_ms_to_time(ts)?,
                 df.shape().0
             );
 
@@ -344,11 +553,22 @@ impl API<'_> {
 
     //
 
+    pub fn tickers_get(
+        &mut self,
+    ) -> Result<HashMap<String, crate::structs::Ticker>, Box<dyn std::error::Error>> {
+        let url = format!("{}/api/v3/ticker/24hr", self.client.url);
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+        tickers_deserialize(&response)
+    }
+
+    //
+
     pub fn klines_get(
         &mut self,
         pair: &str,
         limit: Option<usize>,
-        end_time: Option<u64>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
     ) -> Result<DataFrame, Box<dyn std::error::Error>> {
         //
 
@@ -359,10 +579,13 @@ impl API<'_> {
         if let Some(x) = limit {
             url = format!("{}&limit={}", url, x);
         }
+        if let Some(x) = start_time {
+            url = format!("{}&startTime={}", url, x);
+        }
         if let Some(x) = end_time {
             url = format!("{}&endTime={}", url, x);
         }
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
         Ok(klines_deserialize(&response)?
             .lazy()
             .with_column(lit(pair).alias("pair"))
@@ -376,7 +599,8 @@ impl API<'_> {
     pub fn trades_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         info!("{} trades started", self.label);
 
-        let dir_path = crate::paths::dir_trades().join(self.label);
+        let dir_path =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name);
         debug!(
             "{} trades target directory: {}",
             self.label,
@@ -384,34 +608,7 @@ impl API<'_> {
         );
         crate::paths::dir_create(&dir_path);
 
-        let file_path_conversions =
-            crate::paths::dir_trades().join(format!("conversions-{}.csv", self.label));
-        let (conversion_pairs, conversions) = if file_path_conversions.exists() {
-            let mut schema: Schema = Schema::new();
-            schema.with_column("id".into(), DataType::UInt64);
-            schema.with_column("orderid".into(), DataType::UInt64);
-            schema.with_column("price".into(), DataType::Utf8);
-            schema.with_column("qty".into(), DataType::Utf8);
-            schema.with_column("quoteqty".into(), DataType::Utf8);
-            schema.with_column("commission".into(), DataType::Utf8);
-            schema.with_column("time".into(), DataType::UInt32);
-            schema.with_column("recorded_at".into(), DataType::UInt32);
-            let conversions = crate::csv_read(&file_path_conversions, Some(schema))?;
-            let conversion_pairs = conversions
-                .clone()
-                .collect()?
-                .column("symbol")?
-                .utf8()?
-                .into_iter()
-                .map(|x| Ok(x.ok_or("pair not found")?.to_string()))
-                .collect::<Result<Vec<String>, Box<dyn std::error::Error>>>()?;
-            info!("conversion pairs are {:?}", conversion_pairs);
-            (conversion_pairs, conversions)
-        } else {
-            (Vec::default(), LazyFrame::default())
-        };
-
-        let pairs = self
+        let mut pairs = self
             .pairs
             .iter()
             .filter(|(_, v)| {
@@ -423,35 +620,209 @@ impl API<'_> {
             })
             .map(|(k, _)| k.clone())
             .collect::<Vec<String>>();
+        pairs.sort();
+
+        let file_path_sweep = dir_path.join("sweep.json");
+        let mut sweep = sweep_read(&file_path_sweep)?;
+        let ts_start = crate::utc_ms()?;
+        let ts_full_sweep = crate::ti_ms(&self.config_app.trades.full_sweep)?;
+        let full_sweep = !self.config_app.trades.activity_only
+            | sweep.ts_full.is_none_or(|x| ts_start - x >= ts_full_sweep);
+        if full_sweep {
+            info!("{} trades full sweep", self.label);
+        } else {
+            let assets = self.assets_active_get(&mut sweep)?;
+            debug!("active assets are {:?}", assets);
+            pairs.retain(|x| {
+                dir_path.join(format!("{x}.feather")).is_file()
+                    | self
+                        .pairs
+                        .get(x)
+                        .is_some_and(|v| assets.contains(&v.target))
+            });
+        }
 
         let n_pairs = pairs.len();
         debug!("number of pairs to get trades for: {}", n_pairs);
-        for (index, pair) in pairs.iter().enumerate() {
+        crate::api::pairs_run(self, self.client.concurrency, &pairs, |api, index, pair| {
             info!("{} / {} - {}", index + 1, n_pairs, pair);
-            self.trades_pair_get(&pair, &conversion_pairs, &conversions)?;
+            api.trades_pair_get(pair)
+        })?;
+        if full_sweep {
+            sweep.ts_full = Some(ts_start);
         }
+        crate::json_write(&sweep, &file_path_sweep)?;
 
         Ok(())
     }
 
     //
 
-    fn trades_pair_get(
+    /// Assets held, deposited, withdrawn or (optionally) paid as dividends.
+    fn assets_active_get(
         &mut self,
-        pair: &str,
-        conversion_pairs: &Vec<String>,
-        conversions: &LazyFrame,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = crate::paths::dir_trades()
-            .join(self.label)
-            .join(format!("{pair}.feather"));
+        sweep: &mut Sweep,
+    ) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+        let mut assets = HashSet::new();
+
+        let account: serde_json::Value =
+            serde_json::from_str(&self.signed_get("/api/v3/account", Vec::new())?)?;
+        for balance in account["balances"].as_array().ok_or("balances not found")? {
+            let amount = |name: &str| -> Result<f64, Box<dyn std::error::Error>> {
+                Ok(balance[name]
+                    .as_str()
+                    .ok_or(format!("{} not found", name))?
+                    .parse::<f64>()?)
+            };
+            if amount("free")? + amount("locked")? > 0.0 {
+                assets.insert(
+                    balance["asset"]
+                        .as_str()
+                        .ok_or("asset not found")?
+                        .to_string(),
+                );
+            }
+        }
+
+        self.deposit_coins_get(sweep)?;
+        assets.extend(sweep.deposit_coins.iter().cloned());
+
+        let file_path_withdrawals = crate::paths::dir_account(
+            &crate::paths::dir_withdrawals(),
+            self.label,
+            &self.account.name,
+        )
+        .join("withdrawals.feather");
+        if file_path_withdrawals.is_file() {
+            let df = crate::feather_read(&file_path_withdrawals)?
+                .select([col("coin").unique()])
+                .collect()?;
+            assets.extend(
+                df.column("coin")?
+                    .utf8()?
+                    .into_iter()
+                    .flatten()
+                    .map(|x| x.to_string()),
+            );
+        }
+
+        if self.config_app.trades.activity_dividends {
+            let dividends: serde_json::Value = serde_json::from_str(&self.signed_get(
+                "/sapi/v1/asset/assetDividend",
+                Vec::from([String::from("limit=500")]),
+            )?)?;
+            for dividend in dividends["rows"].as_array().ok_or("rows not found")? {
+                assets.insert(
+                    dividend["asset"]
+                        .as_str()
+                        .ok_or("asset not found")?
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(assets)
+    }
+
+    //
+
+    /// Adds the coins deposited after the last scan, the first one starts at the withdrawals start.
+    ///
+    /// Without a window only the last 90 days are served.
+    fn deposit_coins_get(&mut self, sweep: &mut Sweep) -> Result<(), Box<dyn std::error::Error>> {
+        let limit = self.config_app.withdrawals.limit;
+
+        let mut ts_start = match sweep.ts_deposits {
+            Some(x) => x + 1,
+            None => crate::date_to_unix_ms_tz(
+                &self.config_app.withdrawals.ts_start,
+                crate::timezone_parse(&self.config_app.timezone)?,
+            )?,
+        };
+        let ts_now = crate::utc_ms()?;
+        while ts_start < ts_now {
+            // both bounds are inclusive
+            let ts_end =
+                (crate::interval_add_ms(ts_start, &self.config_app.withdrawals.ts_window, 1)? - 1)
+                    .min(ts_now);
+            let mut offset = 0;
+            loop {
+                let deposits: Vec<serde_json::Value> = serde_json::from_str(&self.signed_get(
+                    "/sapi/v1/capital/deposit/hisrec",
+                    Vec::from([
+                        format!("startTime={}", ts_start),
+                        format!("endTime={}", ts_end),
+                        format!("offset={}", offset),
+                        format!("limit={}", limit),
+                    ]),
+                )?)?;
+                for deposit in deposits.iter() {
+                    sweep.deposit_coins.insert(
+                        deposit["coin"]
+                            .as_str()
+                            .ok_or("coin not found")?
+                            .to_string(),
+                    );
+                }
+                if (deposits.len() as u64) < limit {
+                    break;
+                }
+                offset += limit;
+            }
+            ts_start = ts_end + 1;
+        }
+        sweep.ts_deposits = Some(ts_now);
+
+        Ok(())
+    }
+
+    //
+
+    /// Signed GET request of `path` with `params`.
+    fn signed_get(
+        &mut self,
+        path: &str,
+        params: Vec<String>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let url = self.signed_url(path, params, self.config_app.trades.recvwindow)?;
+        crate::api::request_get(&self.client, crate::api::Request::Get(&url))
+    }
+
+    //
+
+    /// Signed url of `path` with `params`.
+    fn signed_url(
+        &self,
+        path: &str,
+        params: Vec<String>,
+        recvwindow: u64,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut params = params;
+        params.push(format!("recvWindow={}", recvwindow));
+        params.push(format!("timestamp={}", crate::utc_ms()?));
+        let params = params.join("&");
+        let signature = signature_get(&self.secret, &params)?;
+
+        Ok(format!(
+            "{}{}?{}&signature={}",
+            self.client.url, path, params, signature
+        ))
+    }
+
+    //
+
+    fn trades_pair_get(&mut self, pair: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file_path =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name)
+                .join(format!("{pair}.feather"));
         debug!("{} file_path is {}", pair, file_path.as_path().display());
 
         let mut from_id = 0;
         let mut trades_previous = LazyFrame::default();
         if file_path.exists() {
             trades_previous = crate::feather_read(&file_path)?;
-            from_id = crate::column_maxu(trades_previous.clone(), "id")?;
+            // fromId is inclusive, the last stored trade is not requested again
+            from_id = crate::column_maxu(trades_previous.clone(), "id")? + 1;
         };
 
         let mut trades_new = Vec::new();
@@ -464,7 +835,8 @@ impl API<'_> {
                 batch
                     .clone()
                     .lazy()
-                    .with_column(lit(crate::utc_ms()?).alias("recorded_at")),
+                    .with_column(lit(crate::utc_ms()?).alias("recorded_at"))
+                    .with_column(lit(self.account.name.as_str()).alias("account")),
             );
             from_id = crate::column_maxu(batch.clone().lazy(), "id")? + 1;
             batch = self.trades_pair_batch_get(pair, from_id)?;
@@ -473,21 +845,7 @@ impl API<'_> {
 
         if trades_new.len() > 0 {
             trades_new.insert(0, trades_previous);
-            let mut output = concat(trades_new, true, true)?.collect()?;
-            if conversion_pairs.contains(&pair.to_string()) {
-                info!("conversion trades for {}", pair);
-                output = concat(
-                    Vec::from([
-                        output.lazy(),
-                        conversions.clone().filter(col("symbol").eq(lit(pair))),
-                    ]),
-                    true,
-                    true,
-                )?
-                .collect()?;
-            }
-            output = output
-                .lazy()
+            let mut output = concat(trades_new, true, true)?
                 .unique_stable(None, UniqueKeepStrategy::First)
                 .sort_by_exprs([col("time"), col("id")], [false, false], false)
                 .collect()?;
@@ -513,12 +871,12 @@ impl API<'_> {
             format!("timestamp={}", crate::utc_ms()?),
         ])
         .join("&");
-        let signature = signature_get(&params)?;
+        let signature = signature_get(&self.secret, &params)?;
         let url = format!(
             "{}/api/v3/myTrades?{}&signature={}",
             self.client.url, params, signature
         );
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
         // println!("{}", response.clone());
 
         Ok(trades_deserialize(&response)?)
@@ -527,7 +885,11 @@ impl API<'_> {
     //
 
     pub fn withdrawals_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let dir_path = crate::paths::dir_withdrawals().join(self.label);
+        let dir_path = crate::paths::dir_account(
+            &crate::paths::dir_withdrawals(),
+            self.label,
+            &self.account.name,
+        );
         debug!(
             "{} withdrawals target directory: {}",
             self.label,
@@ -537,11 +899,14 @@ impl API<'_> {
         let file_path = dir_path.join(format!("withdrawals.feather"));
         debug!("withdrawals file_path is {}", file_path.as_path().display());
 
-        let mut ts_last = crate::date_to_unix_ms(&self.config_app.withdrawals.ts_start)?;
+        let mut ts_last = crate::date_to_unix_ms_tz(
+            &self.config_app.withdrawals.ts_start,
+            crate::timezone_parse(&self.config_app.timezone)?,
+        )?;
         let mut withdrawals_previous = LazyFrame::default();
         if file_path.exists() {
             withdrawals_previous = crate::feather_read(&file_path)?;
-            ts_last = crate::column_maxu(withdrawals_previous.clone(), "applytime")?
+            ts_last = crate::column_maxi(withdrawals_previous.clone(), "applytime")?
         };
 
         let mut withdrawals_new = Vec::new();
@@ -555,16 +920,18 @@ impl API<'_> {
                     batch
                         .clone()
                         .lazy()
-                        .with_column(lit(crate::utc_ms()?).alias("recorded_at")),
+                        .with_column(lit(crate::utc_ms()?).alias("recorded_at"))
+                        .with_column(lit(self.account.name.as_str()).alias("account")),
                 );
-                let ts_last_new = crate::column_maxu(batch.clone().lazy(), "applytime")?;
+                let ts_last_new = crate::column_maxi(batch.clone().lazy(), "applytime")?;
                 if ts_last != ts_last_new {
                     ts_last = ts_last_new;
                 } else {
                     ts_last += 1;
                 }
             } else {
-                ts_last += crate::ti_ms(&self.config_app.withdrawals.ts_window)?;
+                ts_last =
+                    crate::interval_add_ms(ts_last, &self.config_app.withdrawals.ts_window, 1)?;
             }
             batch = self.withdrawals_batch_get(ts_last)?;
         }
@@ -592,7 +959,7 @@ impl API<'_> {
 
     fn withdrawals_batch_get(
         &mut self,
-        ts_start: u64,
+        ts_start: i64,
     ) -> Result<DataFrame, Box<dyn std::error::Error>> {
         let params = Vec::from([
             format!("status={}", self.config_app.withdrawals.status),
@@ -601,83 +968,1143 @@ impl API<'_> {
             format!("startTime={}", ts_start),
             format!(
                 "endTime={}",
-                ts_start + crate::ti_ms(&self.config_app.withdrawals.ts_window)?
+                crate::interval_add_ms(ts_start, &self.config_app.withdrawals.ts_window, 1)?
             ),
             format!("timestamp={}", crate::utc_ms()?),
         ])
         .join("&");
-        let signature = signature_get(&params)?;
+        let signature = signature_get(&self.secret, &params)?;
         let url = format!(
             "{}/sapi/v1/capital/withdraw/history?{}&signature={}",
             self.client.url, params, signature
         );
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
 
         Ok(withdrawals_deserialize(&response)?)
     }
-}
 
-//
+    //
 
-fn klines_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
-    let rows: Vec<(
-        u64,    // Kline open time
-        String, // Open price
-        String, // High price
-        String, // Low price
-        String, // Close price
-        String, // Volume
-        u64,    // Kline Close time
-        String, // Quote asset volume
-        u32,    // Number of trades
-        String, // Taker buy base asset volume
-        String, // Taker buy quote asset volume
-        String, // Unused field, ignore.
-    )> = serde_json::from_str(&response)?;
+    /// Convert, dust and fiat history as trades, each source in its own file of the trades store.
+    pub fn conversions_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("{} conversions started", self.label);
 
-    let mut ts: Vec<u32> = Vec::new();
-    let mut open: Vec<f32> = Vec::new();
-    let mut high: Vec<f32> = Vec::new();
-    let mut low: Vec<f32> = Vec::new();
-    let mut close: Vec<f32> = Vec::new();
-    let mut volume: Vec<f32> = Vec::new();
-    let mut trades: Vec<u32> = Vec::new();
+        self.conversions_source_get("convert", Self::convert_batch_get)?;
+        self.conversions_csv_import()?;
+        self.conversions_source_get("dribblet", Self::dribblet_batch_get)?;
+        self.conversions_source_get("fiat", Self::fiat_batch_get)?;
 
-    for row in rows.iter() {
-        ts.push((row.0 / 1000).try_into()?);
-        open.push(row.1.parse()?);
-        high.push(row.2.parse()?);
-        low.push(row.3.parse()?);
-        close.push(row.4.parse()?);
-        volume.push(row.7.parse()?);
-        trades.push(row.8);
+        Ok(())
     }
 
-    Ok(DataFrame::new(Vec::from([
-        Series::new("ts", ts),
-        Series::new("open", open),
-        Series::new("high", high),
-        Series::new("low", low),
-        Series::new("close", close),
-        Series::new("quote asset volume", volume),
-        Series::new("number of trades", trades),
-    ]))?)
-}
+    //
 
-//
+    /// Imports the hand-curated `conversions-{label}.csv` of earlier versions into the convert
+    /// trades of the first account once, the file is kept as `conversions-{label}.csv.migrated`.
+    fn conversions_csv_import(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let file_path_csv =
+            crate::paths::dir_trades().join(format!("conversions-{}.csv", self.label));
+        let account_first = crate::api::accounts_get(self.label, self.config_app)?
+            .first()
+            .is_some_and(|x| x.name == self.account.name);
+        if !file_path_csv.is_file() | !account_first {
+            return Ok(());
+        }
 
-#[derive(serde::Deserialize)]
-struct Trade {
-    #[serde(alias = "symbol")]
-    symbol: String,
-    #[serde(alias = "id")]
-    id: u64,
-    #[serde(alias = "orderId")]
-    orderid: u64,
-    #[serde(alias = "orderListId")]
-    orderlistid: i64,
-    #[serde(alias = "price")]
-    price: String,
+        let mut schema: Schema = Schema::new();
+        for name in [
+            "symbol",
+            "price",
+            "qty",
+            "quoteqty",
+            "commission",
+            "commissionasset",
+        ] {
+            schema.with_column(name.into(), DataType::Utf8);
+        }
+        // times were stored as seconds before, milliseconds are at least 10^11 since 1973
+        let ms = |name: &str| {
+            when(col(name).lt(lit(100_000_000_000_i64)))
+                .then(col(name) * lit(1000_i64))
+                .otherwise(col(name))
+        };
+        let conversions = LazyCsvReader::new(&file_path_csv)
+            .has_header(true)
+            .with_dtype_overwrite(Some(&schema))
+            .finish()?
+            .select([
+                col("symbol"),
+                col("id").cast(DataType::UInt64),
+                col("orderid").cast(DataType::UInt64),
+                col("orderlistid").cast(DataType::Int64),
+                col("price"),
+                col("qty"),
+                col("quoteqty"),
+                col("commission"),
+                col("commissionasset"),
+                ms("time").cast(DataType::Int64).alias("time"),
+                col("isbuyer").cast(DataType::Boolean),
+                col("ismaker").cast(DataType::Boolean),
+                col("isbestmatch").cast(DataType::Boolean),
+                ms("recorded_at").cast(DataType::Int64).alias("recorded_at"),
+                lit(self.account.name.as_str()).alias("account"),
+            ]);
+
+        let file_path =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name)
+                .join("convert.feather");
+        let mut trades = Vec::from([conversions]);
+        if file_path.is_file() {
+            trades.insert(0, crate::feather_read(&file_path)?);
+        }
+        // trades of the convert history win over the curated ones
+        let mut output = concat(trades, true, true)?
+            .unique_stable(
+                Some(Vec::from(["id".to_string(), "symbol".to_string()])),
+                UniqueKeepStrategy::First,
+            )
+            .sort_by_exprs([col("time"), col("id")], [false, false], false)
+            .collect()?;
+        crate::feather_write(&mut output, &file_path)?;
+        std::fs::rename(&file_path_csv, file_path_csv.with_extension("csv.migrated"))?;
+        info!(
+            "{} conversions of {} migrated into {}",
+            self.label,
+            file_path_csv.as_path().display(),
+            file_path.as_path().display()
+        );
+
+        Ok(())
+    }
+
+    //
+
+    /// Requests `source` by `batch_get` in windows from the last stored trade on.
+    fn conversions_source_get<F>(
+        &mut self,
+        source: &str,
+        batch_get: F,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: Fn(&mut Self, i64, i64) -> Result<DataFrame, Box<dyn std::error::Error>>,
+    {
+        let dir_path =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name);
+        let file_path = dir_path.join(format!("{source}.feather"));
+        debug!("{} file_path is {}", source, file_path.as_path().display());
+
+        let mut ts_start = crate::date_to_unix_ms_tz(
+            &self.config_app.conversions.ts_start,
+            crate::timezone_parse(&self.config_app.timezone)?,
+        )?;
+        let mut trades_previous = LazyFrame::default();
+        if file_path.exists() {
+            trades_previous = crate::feather_read(&file_path)?;
+            ts_start = crate::column_maxi(trades_previous.clone(), "time")? + 1;
+            // fiat payments still processing are requested again until they are final
+            if trades_previous.schema()?.get("status").is_some() {
+                let tss_processing = crate::column_values_i64(
+                    trades_previous
+                        .clone()
+                        .filter(col("status").eq(lit("Processing"))),
+                    "time",
+                )?;
+                if let Some(ts) = tss_processing.into_iter().min() {
+                    ts_start = ts_start.min(ts);
+                }
+            }
+        };
+
+        let mut trades_new = self.windows_get(
+            source,
+            ts_start,
+            &self.config_app.conversions.ts_window,
+            batch_get,
+        )?;
+        if !trades_new.is_empty() {
+            trades_new.insert(0, trades_previous);
+            // a trade requested again replaces the stored one, fiat payments change their status
+            let mut output = concat(trades_new, true, true)?
+                .unique_stable(
+                    Some(Vec::from(["id".to_string(), "symbol".to_string()])),
+                    UniqueKeepStrategy::Last,
+                )
+                .sort_by_exprs([col("time"), col("id")], [false, false], false)
+                .collect()?;
+            if output.schema().get("status").is_some() {
+                output = output
+                    .lazy()
+                    .filter(
+                        col("status")
+                            .eq(lit("Completed"))
+                            .or(col("status").eq(lit("Processing"))),
+                    )
+                    .collect()?;
+            }
+            crate::feather_write(&mut output, &file_path)?;
+        }
+
+        Ok(())
+    }
+
+    //
+
+    fn convert_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        let mut batches = Vec::new();
+        let mut ts_start = ts_start;
+        loop {
+            let url = self.signed_url(
+                "/sapi/v1/convert/tradeFlow",
+                Vec::from([
+                    format!("startTime={}", ts_start),
+                    format!("endTime={}", ts_end),
+                    String::from("limit=1000"),
+                ]),
+                self.config_app.conversions.recvwindow,
+            )?;
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            let batch = convert_deserialize(&response)?;
+            let more_data = serde_json::from_str::<serde_json::Value>(&response)?["moreData"]
+                .as_bool()
+                .unwrap_or_default();
+            if !more_data | (batch.height() == 0) {
+                batches.push(batch.lazy());
+                break;
+            }
+            ts_start = crate::column_maxi(batch.clone().lazy(), "time")? + 1;
+            batches.push(batch.lazy());
+        }
+
+        Ok(concat(batches, true, true)?.collect()?)
+    }
+
+    //
+
+    fn dribblet_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        const LIMIT: usize = 100;
+        let mut batches = Vec::new();
+        let mut ts_end = ts_end;
+        // only the last 100 dribblets are returned, older ones are requested by moving the end back
+        loop {
+            let url = self.signed_url(
+                "/sapi/v1/asset/dribblet",
+                Vec::from([
+                    format!("startTime={}", ts_start),
+                    format!("endTime={}", ts_end),
+                ]),
+                self.config_app.conversions.recvwindow,
+            )?;
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            let dribblets = serde_json::from_str::<serde_json::Value>(&response)?;
+            let tss = dribblets["userAssetDribblets"]
+                .as_array()
+                .map(|x| {
+                    x.iter()
+                        .map(|x| x["operateTime"].as_i64().ok_or("operateTime not found"))
+                        .collect::<Result<Vec<i64>, &str>>()
+                })
+                .transpose()?
+                .unwrap_or_default();
+            batches.push(dribblet_deserialize(&response)?.lazy());
+            if tss.len() < LIMIT {
+                break;
+            }
+            // the oldest millisecond is requested again, its rows may not all have fit
+            let ts_first = tss.into_iter().min().ok_or("operateTime not found")?;
+            ts_end = if ts_first < ts_end {
+                ts_first
+            } else {
+                ts_first - 1
+            };
+            if ts_end < ts_start {
+                break;
+            }
+        }
+
+        Ok(concat(batches, true, true)?
+            .unique_stable(None, UniqueKeepStrategy::First)
+            .collect()?)
+    }
+
+    //
+
+    fn fiat_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        const ROWS: u64 = 500;
+        let mut batches = Vec::new();
+        // 0 is buy, 1 is sell
+        for transaction_type in [0, 1] {
+            let mut page = 1;
+            loop {
+                let url = self.signed_url(
+                    "/sapi/v1/fiat/payments",
+                    Vec::from([
+                        format!("transactionType={}", transaction_type),
+                        format!("beginTime={}", ts_start),
+                        format!("endTime={}", ts_end),
+                        format!("page={}", page),
+                        format!("rows={}", ROWS),
+                    ]),
+                    self.config_app.conversions.recvwindow,
+                )?;
+                let response =
+                    crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+                batches.push(fiat_deserialize(&response)?.lazy());
+                let total = serde_json::from_str::<serde_json::Value>(&response)?["total"]
+                    .as_u64()
+                    .unwrap_or_default();
+                if page * ROWS >= total {
+                    break;
+                }
+                page += 1;
+            }
+        }
+
+        Ok(concat(batches, true, true)?.collect()?)
+    }
+
+    //
+
+    /// Universal and sub-account transfers of the account.
+    pub fn transfers_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("{} {} transfers started", self.label, self.account.name);
+
+        let dir_path = crate::paths::dir_account(
+            &crate::paths::dir_transfers(),
+            self.label,
+            &self.account.name,
+        );
+        let file_path = dir_path.join("transfers.feather");
+        debug!("transfers file_path is {}", file_path.as_path().display());
+
+        let config_app = self.config_app;
+        let (transfers_previous, ts_last) = ledger_read(&file_path)?;
+        let ts_start = |source: &str| -> Result<i64, Box<dyn std::error::Error>> {
+            match ts_last.get(source) {
+                Some(x) => Ok(x + 1),
+                None => crate::date_to_unix_ms_tz(
+                    &config_app.transfers.ts_start,
+                    crate::timezone_parse(&config_app.timezone)?,
+                ),
+            }
+        };
+
+        let mut transfers_new = Vec::from([transfers_previous]);
+        for kind in config_app.transfers.types.iter() {
+            transfers_new.extend(self.windows_get(
+                kind,
+                ts_start(kind)?,
+                &config_app.transfers.ts_window,
+                |api, ts_start, ts_end| api.universal_batch_get(kind, ts_start, ts_end),
+            )?);
+        }
+        if config_app.transfers.sub_accounts {
+            transfers_new.extend(self.windows_get(
+                "sub-account",
+                ts_start("sub-account")?,
+                &config_app.transfers.ts_window,
+                Self::sub_account_batch_get,
+            )?);
+        }
+        ledger_write(
+            transfers_new,
+            &["source", "id", "asset", "amount", "time"],
+            &file_path,
+        )?;
+
+        Ok(())
+    }
+
+    //
+
+    fn universal_batch_get(
+        &mut self,
+        kind: &str,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        self.pages_get(
+            "/sapi/v1/asset/transfer",
+            Vec::from([format!("type={}", kind)]),
+            ts_start,
+            ts_end,
+            self.config_app.transfers.recvwindow,
+            universal_deserialize,
+        )
+    }
+
+    //
+
+    // the master account lists transfers of all its sub-accounts, a sub-account its own
+    fn sub_account_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        const LIMIT: usize = 500;
+        let mut batches = Vec::new();
+        let mut page = 1;
+        let mut ts_start = ts_start;
+        loop {
+            let url = if self.account.sub_account {
+                self.signed_url(
+                    "/sapi/v1/sub-account/transfer/subUserHistory",
+                    Vec::from([
+                        format!("startTime={}", ts_start),
+                        format!("endTime={}", ts_end),
+                        format!("limit={}", LIMIT),
+                    ]),
+                    self.config_app.transfers.recvwindow,
+                )?
+            } else {
+                self.signed_url(
+                    "/sapi/v1/sub-account/universalTransfer",
+                    Vec::from([
+                        format!("startTime={}", ts_start),
+                        format!("endTime={}", ts_end),
+                        format!("page={}", page),
+                        format!("limit={}", LIMIT),
+                    ]),
+                    self.config_app.transfers.recvwindow,
+                )?
+            };
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            let batch = if self.account.sub_account {
+                sub_history_deserialize(&response)?
+            } else {
+                sub_universal_deserialize(&response)?
+            };
+            if batch.height() < LIMIT {
+                batches.push(batch.lazy());
+                break;
+            }
+            // the history of a sub-account is not paged, it continues after its last record
+            if self.account.sub_account {
+                ts_start = crate::column_maxi(batch.clone().lazy(), "time")? + 1;
+            } else {
+                page += 1;
+            }
+            batches.push(batch.lazy());
+        }
+
+        Ok(concat(batches, true, true)?.collect()?)
+    }
+
+    //
+
+    /// Dividends, earn and staking rewards, all sources in one income ledger.
+    pub fn income_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("{} income started", self.label);
+
+        let dir_path =
+            crate::paths::dir_account(&crate::paths::dir_income(), self.label, &self.account.name);
+        let file_path = dir_path.join("income.feather");
+        debug!("income file_path is {}", file_path.as_path().display());
+
+        let config_app = self.config_app;
+        let (income_previous, ts_last) = ledger_read(&file_path)?;
+        let ts_start = |source: &str| -> Result<i64, Box<dyn std::error::Error>> {
+            match ts_last.get(source) {
+                Some(x) => Ok(x + 1),
+                None => crate::date_to_unix_ms_tz(
+                    &config_app.income.ts_start,
+                    crate::timezone_parse(&config_app.timezone)?,
+                ),
+            }
+        };
+
+        let mut income_new = Vec::from([income_previous]);
+        income_new.extend(self.windows_get(
+            "dividend",
+            ts_start("dividend")?,
+            &config_app.income.ts_window,
+            Self::dividends_batch_get,
+        )?);
+        // each reward type keeps its own cursor, their records arrive independently
+        for kind in ["BONUS", "REALTIME", "REWARDS"] {
+            let source = format!("flexible-{}", kind);
+            income_new.extend(self.windows_get(
+                &source,
+                ts_start(&source)?,
+                &config_app.income.ts_window,
+                |api: &mut Self, ts_start, ts_end| api.flexible_batch_get(kind, ts_start, ts_end),
+            )?);
+        }
+        income_new.extend(self.windows_get(
+            "locked",
+            ts_start("locked")?,
+            &config_app.income.ts_window,
+            Self::locked_batch_get,
+        )?);
+        income_new.extend(self.windows_get(
+            "eth-staking",
+            ts_start("eth-staking")?,
+            &config_app.income.ts_window,
+            Self::eth_staking_batch_get,
+        )?);
+        ledger_write(
+            income_new,
+            &["source", "id", "asset", "amount", "type", "time"],
+            &file_path,
+        )?;
+
+        Ok(())
+    }
+
+    //
+
+    /// Requests `source` by `batch_get` in windows of `ts_window` from `ts_start` to now.
+    fn windows_get<F>(
+        &mut self,
+        source: &str,
+        ts_start: i64,
+        ts_window: &str,
+        batch_get: F,
+    ) -> Result<Vec<LazyFrame>, Box<dyn std::error::Error>>
+    where
+        F: Fn(&mut Self, i64, i64) -> Result<DataFrame, Box<dyn std::error::Error>>,
+    {
+        let mut ts_start = ts_start;
+        let mut records_new = Vec::new();
+        let mut n_records_new: u64 = 0;
+
+        let ts_now = crate::utc_ms()?;
+        while ts_start < ts_now {
+            // both bounds are inclusive
+            let ts_end = (crate::interval_add_ms(ts_start, ts_window, 1)? - 1).min(ts_now);
+            let batch = batch_get(self, ts_start, ts_end)?;
+            if batch.height() > 0 {
+                n_records_new += batch.height() as u64;
+                records_new.push(
+                    batch
+                        .lazy()
+                        .with_column(lit(crate::utc_ms()?).alias("recorded_at"))
+                        .with_column(lit(self.account.name.as_str()).alias("account")),
+                );
+            }
+            ts_start = ts_end + 1;
+        }
+        info!("number of new {} records is {}", source, n_records_new);
+
+        Ok(records_new)
+    }
+
+    //
+
+    fn dividends_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        const LIMIT: usize = 500;
+        let mut batches = Vec::new();
+        let mut ts_end = ts_end;
+        // records come newest first, older ones are requested by moving the end back
+        loop {
+            let url = self.signed_url(
+                "/sapi/v1/asset/assetDividend",
+                Vec::from([
+                    format!("startTime={}", ts_start),
+                    format!("endTime={}", ts_end),
+                    format!("limit={}", LIMIT),
+                ]),
+                self.config_app.income.recvwindow,
+            )?;
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            let batch = dividends_deserialize(&response)?;
+            if batch.height() < LIMIT {
+                batches.push(batch.lazy());
+                break;
+            }
+            // the oldest millisecond is requested again, its rows may not all have fit
+            let ts_first = crate::column_mini(batch.clone().lazy(), "time")?;
+            ts_end = if ts_first < ts_end {
+                ts_first
+            } else {
+                warn!(
+                    "more than {} dividends at {}, older ones are requested",
+                    LIMIT, ts_first
+                );
+                ts_first - 1
+            };
+            batches.push(batch.lazy());
+            if ts_end < ts_start {
+                break;
+            }
+        }
+
+        Ok(concat(batches, true, true)?
+            .unique_stable(
+                Some(Vec::from(["id".to_string()])),
+                UniqueKeepStrategy::First,
+            )
+            .collect()?)
+    }
+
+    //
+
+    fn flexible_batch_get(
+        &mut self,
+        kind: &str,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        self.pages_get(
+            "/sapi/v1/simple-earn/flexible/history/rewardsRecord",
+            Vec::from([format!("type={}", kind)]),
+            ts_start,
+            ts_end,
+            self.config_app.income.recvwindow,
+            flexible_deserialize,
+        )
+    }
+
+    //
+
+    fn locked_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        self.pages_get(
+            "/sapi/v1/simple-earn/locked/history/rewardsRecord",
+            Vec::new(),
+            ts_start,
+            ts_end,
+            self.config_app.income.recvwindow,
+            locked_deserialize,
+        )
+    }
+
+    //
+
+    fn eth_staking_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        self.pages_get(
+            "/sapi/v1/eth-staking/eth/history/rewardsHistory",
+            Vec::new(),
+            ts_start,
+            ts_end,
+            self.config_app.income.recvwindow,
+            eth_staking_deserialize,
+        )
+    }
+
+    //
+
+    /// All `current`/`size` pages of `path` between two inclusive timestamps.
+    fn pages_get(
+        &mut self,
+        path: &str,
+        params: Vec<String>,
+        ts_start: i64,
+        ts_end: i64,
+        recvwindow: u64,
+        deserialize: fn(&str) -> Result<DataFrame, Box<dyn std::error::Error>>,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        const SIZE: u64 = 100;
+        let mut batches = Vec::new();
+        let mut current = 1;
+        loop {
+            let mut params_page = params.clone();
+            params_page.extend([
+                format!("startTime={}", ts_start),
+                format!("endTime={}", ts_end),
+                format!("current={}", current),
+                format!("size={}", SIZE),
+            ]);
+            let url = self.signed_url(path, params_page, recvwindow)?;
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            batches.push(deserialize(&response)?.lazy());
+            let total = serde_json::from_str::<serde_json::Value>(&response)?["total"]
+                .as_u64()
+                .unwrap_or_default();
+            if current * SIZE >= total {
+                break;
+            }
+            current += 1;
+        }
+
+        Ok(concat(batches, true, true)?.collect()?)
+    }
+
+    //
+
+    /// Copy of the api requesting with the `name` client, e.g. binance_futures, as the same account.
+    fn client_api(&self, name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut api = self.clone();
+        api.client = crate::api::client_get(
+            name,
+            &self.account.name,
+            self.config_app,
+            self.client.headers.clone(),
+        )?;
+
+        Ok(api)
+    }
+
+    //
+
+    pub fn margin_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("{} margin started", self.label);
+
+        let mut api = self.client_api("binance_margin")?;
+        let config_app = self.config_app;
+        let dir_trades =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name);
+        let dir_path = dir_trades.join("margin");
+        crate::paths::dir_create(&dir_path);
+
+        let file_path_sweep = dir_trades.join("sweep.json");
+        let mut sweep = sweep_read(&file_path_sweep)?;
+        let ts_start = crate::utc_ms()?;
+        let mut full_sweep = false;
+        let mut pairs = config_app.margin.pairs.clone();
+        if pairs.is_empty() {
+            // base asset by pair
+            let mut bases = HashMap::new();
+            let url = format!("{}/sapi/v1/margin/allPairs", api.client.url);
+            let response = crate::api::request_get(&api.client, crate::api::Request::Get(&url))?;
+            let response_json: Vec<serde_json::Value> = serde_json::from_str(&response)?;
+            for pair in response_json.iter() {
+                let quote = pair["quote"].as_str().ok_or("quote not found")?;
+                if pair["isMarginTrade"].as_bool().unwrap_or_default()
+                    & (!config_app.trades.quote_only
+                        | config_app.trades.quotes.contains(&quote.to_string()))
+                {
+                    bases.insert(
+                        pair["symbol"]
+                            .as_str()
+                            .ok_or("symbol not found")?
+                            .to_string(),
+                        pair["base"].as_str().ok_or("base not found")?.to_string(),
+                    );
+                }
+            }
+            pairs.extend(bases.keys().cloned());
+
+            // the same activity filter as the spot trades, with a full sweep of its own
+            let ts_full_sweep = crate::ti_ms(&config_app.trades.full_sweep)?;
+            full_sweep = !config_app.trades.activity_only
+                | sweep
+                    .ts_full_margin
+                    .is_none_or(|x| ts_start - x >= ts_full_sweep);
+            if full_sweep {
+                info!("{} margin full sweep", self.label);
+            } else {
+                let mut assets = self.assets_active_get(&mut sweep)?;
+                assets.extend(api.margin_assets_get()?);
+                debug!("active margin assets are {:?}", assets);
+                pairs.retain(|x| {
+                    dir_path.join(format!("{x}.feather")).is_file()
+                        | bases.get(x).is_some_and(|v| assets.contains(v))
+                });
+            }
+        }
+        pairs.sort();
+
+        let n_pairs = pairs.len();
+        debug!("number of pairs to get margin trades for: {}", n_pairs);
+        crate::api::pairs_run(&api, api.client.concurrency, &pairs, |api, index, pair| {
+            info!("{} / {} - {}", index + 1, n_pairs, pair);
+            api.fromid_pages_get(
+                "/sapi/v1/margin/myTrades",
+                Vec::from([format!("symbol={}", pair), String::from("isIsolated=FALSE")]),
+                &dir_path.join(format!("{pair}.feather")),
+                config_app.margin.limit,
+                config_app.margin.recvwindow,
+                margin_trades_deserialize,
+            )
+        })?;
+        if full_sweep {
+            sweep.ts_full_margin = Some(ts_start);
+        }
+        crate::json_write(&sweep, &file_path_sweep)?;
+
+        Ok(())
+    }
+
+    //
+
+    /// Assets held or borrowed in the cross margin account.
+    fn margin_assets_get(&mut self) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+        let mut assets = HashSet::new();
+
+        let account: serde_json::Value =
+            serde_json::from_str(&self.signed_get("/sapi/v1/margin/account", Vec::new())?)?;
+        for asset in account["userAssets"]
+            .as_array()
+            .ok_or("userAssets not found")?
+        {
+            let amount = |name: &str| -> Result<f64, Box<dyn std::error::Error>> {
+                Ok(asset[name]
+                    .as_str()
+                    .ok_or(format!("{} not found", name))?
+                    .parse::<f64>()?)
+            };
+            if amount("free")? + amount("locked")? + amount("borrowed")? > 0.0 {
+                assets.insert(
+                    asset["asset"]
+                        .as_str()
+                        .ok_or("asset not found")?
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(assets)
+    }
+
+    //
+
+    /// Futures income first, then the trades of every symbol with income, e.g. commissions.
+    pub fn futures_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("{} futures started", self.label);
+
+        let mut api = self.client_api("binance_futures")?;
+        let config_app = self.config_app;
+        let file_path =
+            crate::paths::dir_account(&crate::paths::dir_income(), self.label, &self.account.name)
+                .join("futures.feather");
+        debug!("futures file_path is {}", file_path.as_path().display());
+
+        let (income_previous, ts_last) = ledger_read(&file_path)?;
+        let ts_start = match ts_last.get("futures") {
+            Some(x) => x + 1,
+            None => crate::date_to_unix_ms_tz(
+                &config_app.futures.ts_start,
+                crate::timezone_parse(&config_app.timezone)?,
+            )?,
+        };
+        let mut income_new = Vec::from([income_previous]);
+        income_new.extend(api.windows_get(
+            "futures",
+            ts_start,
+            &config_app.futures.ts_window,
+            Self::futures_income_batch_get,
+        )?);
+        ledger_write(
+            income_new,
+            &["source", "id", "asset", "type", "time"],
+            &file_path,
+        )?;
+
+        let dir_path =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name)
+                .join("futures");
+        crate::paths::dir_create(&dir_path);
+        let mut symbols = crate::paths::dir_list(&dir_path)?
+            .into_iter()
+            .filter(|x| x.extension == "feather")
+            .map(|x| x.stem)
+            .collect::<HashSet<String>>();
+        if file_path.is_file() {
+            let df = crate::feather_read(&file_path)?
+                .select([col("symbol").unique()])
+                .collect()?;
+            symbols.extend(
+                df.column("symbol")?
+                    .utf8()?
+                    .into_iter()
+                    .flatten()
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_string()),
+            );
+        }
+        let mut symbols = symbols.into_iter().collect::<Vec<String>>();
+        symbols.sort();
+
+        let n_symbols = symbols.len();
+        debug!("number of symbols to get futures trades for: {}", n_symbols);
+        crate::api::pairs_run(
+            &api,
+            api.client.concurrency,
+            &symbols,
+            |api, index, symbol| {
+                info!("{} / {} - {}", index + 1, n_symbols, symbol);
+                api.fromid_pages_get(
+                    "/fapi/v1/userTrades",
+                    Vec::from([format!("symbol={}", symbol)]),
+                    &dir_path.join(format!("{symbol}.feather")),
+                    config_app.futures.limit,
+                    config_app.futures.recvwindow,
+                    futures_trades_deserialize,
+                )
+            },
+        )
+    }
+
+    //
+
+    fn futures_income_batch_get(
+        &mut self,
+        ts_start: i64,
+        ts_end: i64,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        let limit = self.config_app.futures.limit;
+        let mut batches = Vec::new();
+        let mut page = 1;
+        // records of one time may span pages, so the window stays fixed and is paged through
+        loop {
+            let url = self.signed_url(
+                "/fapi/v1/income",
+                Vec::from([
+                    format!("startTime={}", ts_start),
+                    format!("endTime={}", ts_end),
+                    format!("page={}", page),
+                    format!("limit={}", limit),
+                ]),
+                self.config_app.futures.recvwindow,
+            )?;
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            let batch = futures_income_deserialize(&response)?;
+            let full = batch.height() as u64 >= limit;
+            batches.push(batch.lazy());
+            if !full {
+                break;
+            }
+            page += 1;
+        }
+
+        // records added while paging shift the pages, the ones seen twice are dropped
+        Ok(concat(batches, true, true)?
+            .unique_stable(
+                Some(Vec::from([
+                    "id".to_string(),
+                    "asset".to_string(),
+                    "type".to_string(),
+                    "time".to_string(),
+                ])),
+                UniqueKeepStrategy::First,
+            )
+            .collect()?)
+    }
+
+    //
+
+    /// New records of `path` for one pair paged by `fromId`, added to the ones at `file_path`.
+    fn fromid_pages_get(
+        &mut self,
+        path: &str,
+        params: Vec<String>,
+        file_path: &std::path::PathBuf,
+        limit: u64,
+        recvwindow: u64,
+        deserialize: fn(&str) -> Result<DataFrame, Box<dyn std::error::Error>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut from_id = 0;
+        let mut records = Vec::from([LazyFrame::default()]);
+        if file_path.exists() {
+            records[0] = crate::feather_read(file_path)?;
+            // fromId is inclusive, the last stored record is not requested again
+            from_id = crate::column_maxu(records[0].clone(), "id")? + 1;
+        }
+
+        let mut n_records_new: u64 = 0;
+        loop {
+            let mut params_page = params.clone();
+            params_page.extend([format!("fromId={}", from_id), format!("limit={}", limit)]);
+            let url = self.signed_url(path, params_page, recvwindow)?;
+            let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
+            let batch = deserialize(&response)?;
+            if batch.height() == 0 {
+                break;
+            }
+            n_records_new += batch.height() as u64;
+            from_id = crate::column_maxu(batch.clone().lazy(), "id")? + 1;
+            let page_full = batch.height() as u64 >= limit;
+            records.push(
+                batch
+                    .lazy()
+                    .with_column(lit(crate::utc_ms()?).alias("recorded_at"))
+                    .with_column(lit(self.account.name.as_str()).alias("account")),
+            );
+            if !page_full {
+                break;
+            }
+        }
+        info!(
+            "number of new records for {} is {}",
+            file_path.as_path().display(),
+            n_records_new
+        );
+
+        if records.len() > 1 {
+            let mut output = concat(records, true, true)?
+                .unique_stable(None, UniqueKeepStrategy::First)
+                .sort_by_exprs([col("time"), col("id")], [false, false], false)
+                .collect()?;
+            crate::feather_write(&mut output, file_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+//
+
+/// Sweep state at `file_path`, earlier versions stored the time of the last full sweep only.
+fn sweep_read(file_path: &std::path::PathBuf) -> Result<Sweep, Box<dyn std::error::Error>> {
+    if !file_path.is_file() {
+        return Ok(Sweep::default());
+    }
+    let value: serde_json::Value = crate::json_read(file_path)?;
+    match value.as_i64() {
+        Some(x) => Ok(Sweep {
+            ts_full: Some(x),
+            ..Default::default()
+        }),
+        None => Ok(serde_json::from_value(value)?),
+    }
+}
+
+//
+
+/// Stored ledger at `file_path` and the time of its last record per source and, if the
+/// ledger has a type column, per `{source}-{type}`.
+fn ledger_read(
+    file_path: &std::path::PathBuf,
+) -> Result<(LazyFrame, HashMap<String, i64>), Box<dyn std::error::Error>> {
+    let mut ts_last = HashMap::new();
+    if !file_path.exists() {
+        return Ok((LazyFrame::default(), ts_last));
+    }
+    let ledger = crate::feather_read(file_path)?;
+    let df = ledger
+        .clone()
+        .groupby([col("source")])
+        .agg([col("time").max()])
+        .collect()?;
+    for (source, time) in df
+        .column("source")?
+        .utf8()?
+        .into_iter()
+        .zip(df.column("time")?.i64()?)
+    {
+        if let (Some(source), Some(time)) = (source, time) {
+            ts_last.insert(source.to_string(), time);
+        }
+    }
+    if ledger.schema()?.get("type").is_some() {
+        let df = ledger
+            .clone()
+            .groupby([col("source"), col("type").cast(DataType::Utf8)])
+            .agg([col("time").max()])
+            .collect()?;
+        for ((source, kind), time) in df
+            .column("source")?
+            .utf8()?
+            .into_iter()
+            .zip(df.column("type")?.utf8()?)
+            .zip(df.column("time")?.i64()?)
+        {
+            if let (Some(source), Some(kind), Some(time)) = (source, kind, time) {
+                ts_last.insert(format!("{}-{}", source, kind), time);
+            }
+        }
+    }
+
+    Ok((ledger, ts_last))
+}
+
+//
+
+/// Writes the stored ledger (first frame) with the new records, unique on `subset`.
+fn ledger_write(
+    ledger: Vec<LazyFrame>,
+    subset: &[&str],
+    file_path: &std::path::PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if ledger.len() < 2 {
+        return Ok(());
+    }
+    let mut output = concat(ledger, true, true)?
+        .unique_stable(
+            Some(subset.iter().map(|x| x.to_string()).collect()),
+            UniqueKeepStrategy::First,
+        )
+        .sort_by_exprs([col("time"), col("source")], [false, false], false)
+        .collect()?;
+    crate::feather_write(&mut output, file_path)?;
+
+    Ok(())
+}
+
+//
+
+pub fn klines_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rows: Vec<(
+        i64,    // Kline open time
+        String, // Open price
+        String, // High price
+        String, // Low price
+        String, // Close price
+        String, // Volume
+        i64,    // Kline Close time
+        String, // Quote asset volume
+        u32,    // Number of trades
+        String, // Taker buy base asset volume
+        String, // Taker buy quote asset volume
+        String, // Unused field, ignore.
+    )> = serde_json::from_str(&response)?;
+
+    let mut ts: Vec<i64> = Vec::new();
+    let mut open: Vec<f32> = Vec::new();
+    let mut high: Vec<f32> = Vec::new();
+    let mut low: Vec<f32> = Vec::new();
+    let mut close: Vec<f32> = Vec::new();
+    let mut volume: Vec<f32> = Vec::new();
+    let mut trades: Vec<u32> = Vec::new();
+
+    for row in rows.iter() {
+        ts.push(row.0);
+        open.push(row.1.parse()?);
+        high.push(row.2.parse()?);
+        low.push(row.3.parse()?);
+        close.push(row.4.parse()?);
+        volume.push(row.7.parse()?);
+        trades.push(row.8);
+    }
+
+    Ok(DataFrame::new(Vec::from([
+        Series::new("ts", ts),
+        Series::new("open", open),
+        Series::new("high", high),
+        Series::new("low", low),
+        Series::new("close", close),
+        Series::new("quote asset volume", volume),
+        Series::new("number of trades", trades),
+    ]))?)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct Trade {
+    #[serde(alias = "symbol")]
+    symbol: String,
+    #[serde(alias = "id")]
+    id: u64,
+    #[serde(alias = "orderId")]
+    orderid: u64,
+    #[serde(alias = "orderListId")]
+    orderlistid: i64,
+    #[serde(alias = "price")]
+    price: String,
     #[serde(alias = "qty")]
     qty: String,
     #[serde(alias = "quoteQty")]
@@ -687,7 +2114,7 @@ struct Trade {
     #[serde(alias = "commissionAsset")]
     commissionasset: String,
     #[serde(alias = "time")]
-    time: u64,
+    time: i64,
     #[serde(alias = "isBuyer")]
     isbuyer: bool,
     #[serde(alias = "isMaker")]
@@ -698,63 +2125,892 @@ struct Trade {
 
 //
 
-fn trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
-    let rows: Vec<Trade> = serde_json::from_str(&response)?;
+pub fn trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rows: Vec<Trade> = serde_json::from_str(&response)?;
+
+    trades_frame(&rows)
+}
+
+//
+
+fn trades_frame(rows: &[Trade]) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let mut symbol: Vec<String> = Vec::new();
+    let mut id: Vec<u64> = Vec::new();
+    let mut orderid: Vec<u64> = Vec::new();
+    let mut orderlistid: Vec<i64> = Vec::new();
+    let mut price: Vec<String> = Vec::new();
+    let mut qty: Vec<String> = Vec::new();
+    let mut quoteqty: Vec<String> = Vec::new();
+    let mut commission: Vec<String> = Vec::new();
+    let mut commissionasset: Vec<String> = Vec::new();
+    let mut time: Vec<i64> = Vec::new();
+    let mut isbuyer: Vec<bool> = Vec::new();
+    let mut ismaker: Vec<bool> = Vec::new();
+    let mut isbestmatch: Vec<bool> = Vec::new();
+
+    for row in rows.iter() {
+        symbol.push(row.symbol.clone());
+        id.push(row.id);
+        orderid.push(row.orderid);
+        orderlistid.push(row.orderlistid);
+        price.push(row.price.clone());
+        qty.push(row.qty.clone());
+        quoteqty.push(row.quoteqty.clone());
+        commission.push(row.commission.clone());
+        commissionasset.push(row.commissionasset.clone());
+        time.push(row.time);
+        isbuyer.push(row.isbuyer);
+        ismaker.push(row.ismaker);
+        isbestmatch.push(row.isbestmatch);
+    }
+
+    Ok(
+        DataFrame::new(Vec::from([
+            Series::new("symbol", symbol),
+            Series::new("id", id),
+            Series::new("orderid", orderid),
+            Series::new("orderlistid", orderlistid),
+            Series::new("price", price),
+            Series::new("qty", qty),
+            Series::new("quoteqty", quoteqty),
+            Series::new("commission", commission),
+            Series::new("commissionasset", commissionasset),
+            Series::new("time", time),
+            Series::new("isbuyer", isbuyer),
+            Series::new("ismaker", ismaker),
+            Series::new("isbestmatch", isbestmatch),
+        ]))?, // .lazy()
+              // .with_columns([
+              //     col("price").cast(DataType::Decimal(None, Some(8))),
+              //     col("qty").cast(DataType::Decimal(None, Some(8))),
+              //     col("quoteqty").cast(DataType::Decimal(None, Some(8))),
+              //     col("commission").cast(DataType::Decimal(None, Some(8))),
+              // ])
+              // .collect()?
+    )
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct MarginTrade {
+    #[serde(alias = "symbol")]
+    symbol: String,
+    #[serde(alias = "id")]
+    id: u64,
+    #[serde(alias = "orderId")]
+    orderid: u64,
+    #[serde(alias = "price")]
+    price: String,
+    #[serde(alias = "qty")]
+    qty: String,
+    #[serde(alias = "commission")]
+    commission: String,
+    #[serde(alias = "commissionAsset")]
+    commissionasset: String,
+    #[serde(alias = "time")]
+    time: i64,
+    #[serde(alias = "isBuyer")]
+    isbuyer: bool,
+    #[serde(alias = "isMaker")]
+    ismaker: bool,
+    #[serde(alias = "isBestMatch")]
+    isbestmatch: bool,
+}
+
+//
+
+/// Cross margin trades as spot trades, without order lists and with the quote quantity computed.
+pub fn margin_trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rows: Vec<MarginTrade> = serde_json::from_str(response)?;
+    let rows = rows
+        .into_iter()
+        .map(|x| {
+            Ok(Trade {
+                quoteqty: crate::decimal_mul(&x.price, &x.qty)?,
+                symbol: x.symbol,
+                id: x.id,
+                orderid: x.orderid,
+                orderlistid: -1,
+                price: x.price,
+                qty: x.qty,
+                commission: x.commission,
+                commissionasset: x.commissionasset,
+                time: x.time,
+                isbuyer: x.isbuyer,
+                ismaker: x.ismaker,
+                isbestmatch: x.isbestmatch,
+            })
+        })
+        .collect::<Result<Vec<Trade>, Box<dyn std::error::Error>>>()?;
+
+    trades_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct FuturesTrade {
+    #[serde(alias = "symbol")]
+    symbol: String,
+    #[serde(alias = "id")]
+    id: u64,
+    #[serde(alias = "orderId")]
+    orderid: u64,
+    #[serde(alias = "side")]
+    side: String,
+    #[serde(alias = "positionSide")]
+    positionside: String,
+    #[serde(alias = "price")]
+    price: String,
+    #[serde(alias = "qty")]
+    qty: String,
+    #[serde(alias = "quoteQty")]
+    quoteqty: String,
+    #[serde(alias = "realizedPnl")]
+    realizedpnl: String,
+    #[serde(alias = "commission")]
+    commission: String,
+    #[serde(alias = "commissionAsset")]
+    commissionasset: String,
+    #[serde(alias = "time")]
+    time: i64,
+    #[serde(alias = "buyer")]
+    isbuyer: bool,
+    #[serde(alias = "maker")]
+    ismaker: bool,
+}
+
+//
+
+pub fn futures_trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rows: Vec<FuturesTrade> = serde_json::from_str(response)?;
+    let strings = |f: fn(&FuturesTrade) -> &str| rows.iter().map(f).collect::<Vec<&str>>();
+
+    Ok(DataFrame::new(Vec::from([
+        Series::new("symbol", strings(|x| &x.symbol)),
+        Series::new("id", rows.iter().map(|x| x.id).collect::<Vec<u64>>()),
+        Series::new(
+            "orderid",
+            rows.iter().map(|x| x.orderid).collect::<Vec<u64>>(),
+        ),
+        Series::new("side", strings(|x| &x.side)),
+        Series::new("positionside", strings(|x| &x.positionside)),
+        Series::new("price", strings(|x| &x.price)),
+        Series::new("qty", strings(|x| &x.qty)),
+        Series::new("quoteqty", strings(|x| &x.quoteqty)),
+        Series::new("realizedpnl", strings(|x| &x.realizedpnl)),
+        Series::new("commission", strings(|x| &x.commission)),
+        Series::new("commissionasset", strings(|x| &x.commissionasset)),
+        Series::new("time", rows.iter().map(|x| x.time).collect::<Vec<i64>>()),
+        Series::new(
+            "isbuyer",
+            rows.iter().map(|x| x.isbuyer).collect::<Vec<bool>>(),
+        ),
+        Series::new(
+            "ismaker",
+            rows.iter().map(|x| x.ismaker).collect::<Vec<bool>>(),
+        ),
+    ]))?)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct FuturesIncome {
+    #[serde(alias = "symbol")]
+    symbol: String,
+    #[serde(alias = "incomeType")]
+    incometype: String,
+    #[serde(alias = "income")]
+    income: String,
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "time")]
+    time: i64,
+    #[serde(alias = "tranId")]
+    tranid: serde_json::Value,
+    // empty or missing for income without a trade, e.g. funding fees
+    #[serde(default, alias = "tradeId")]
+    tradeid: serde_json::Value,
+}
+
+//
+
+/// Funding fees, commissions, realized PnL and other futures income, trades by `tradeid`.
+pub fn futures_income_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rows: Vec<FuturesIncome> = serde_json::from_str(response)?;
+
+    Ok(DataFrame::new(Vec::from([
+        Series::new("source", vec!["futures"; rows.len()]),
+        Series::new(
+            "id",
+            rows.iter()
+                .map(|x| id_string(&x.tranid))
+                .collect::<Vec<String>>(),
+        ),
+        Series::new(
+            "symbol",
+            rows.iter()
+                .map(|x| x.symbol.as_str())
+                .collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "asset",
+            rows.iter().map(|x| x.asset.as_str()).collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "amount",
+            rows.iter()
+                .map(|x| x.income.as_str())
+                .collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "type",
+            rows.iter()
+                .map(|x| x.incometype.as_str())
+                .collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "tradeid",
+            rows.iter()
+                .map(|x| id_string(&x.tradeid))
+                .collect::<Vec<String>>(),
+        ),
+        Series::new("time", rows.iter().map(|x| x.time).collect::<Vec<i64>>()),
+    ]))?)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct ConvertFlow {
+    #[serde(alias = "list")]
+    list: Vec<Convert>,
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct Convert {
+    #[serde(alias = "orderId")]
+    orderid: u64,
+    #[serde(alias = "orderStatus")]
+    orderstatus: String,
+    #[serde(alias = "fromAsset")]
+    fromasset: String,
+    #[serde(alias = "fromAmount")]
+    fromamount: String,
+    #[serde(alias = "toAsset")]
+    toasset: String,
+    #[serde(alias = "toAmount")]
+    toamount: String,
+    #[serde(alias = "inverseRatio")]
+    inverseratio: String,
+    #[serde(alias = "createTime")]
+    createtime: i64,
+}
+
+//
+
+/// Successful conversions as buys of the target asset paid with the source asset.
+pub fn convert_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let flow: ConvertFlow = serde_json::from_str(response)?;
+
+    let rows = flow
+        .list
+        .into_iter()
+        .filter(|x| x.orderstatus == "SUCCESS")
+        .map(|x| Trade {
+            symbol: format!("{}{}", x.toasset, x.fromasset),
+            id: x.orderid,
+            orderid: x.orderid,
+            orderlistid: -1,
+            price: x.inverseratio,
+            qty: x.toamount,
+            quoteqty: x.fromamount,
+            commission: String::from("0"),
+            commissionasset: x.toasset,
+            time: x.createtime,
+            isbuyer: true,
+            ismaker: false,
+            isbestmatch: true,
+        })
+        .collect::<Vec<Trade>>();
+
+    trades_frame(&rows)
+}
 
-    let mut symbol: Vec<String> = Vec::new();
-    let mut id: Vec<u64> = Vec::new();
-    let mut orderid: Vec<u64> = Vec::new();
-    let mut orderlistid: Vec<i64> = Vec::new();
-    let mut price: Vec<String> = Vec::new();
-    let mut qty: Vec<String> = Vec::new();
-    let mut quoteqty: Vec<String> = Vec::new();
-    let mut commission: Vec<String> = Vec::new();
-    let mut commissionasset: Vec<String> = Vec::new();
-    let mut time: Vec<u64> = Vec::new();
-    let mut isbuyer: Vec<bool> = Vec::new();
-    let mut ismaker: Vec<bool> = Vec::new();
-    let mut isbestmatch: Vec<bool> = Vec::new();
+//
 
-    for row in rows.iter() {
-        symbol.push(row.symbol.clone());
-        id.push(row.id);
-        orderid.push(row.orderid);
-        orderlistid.push(row.orderlistid);
-        price.push(row.price.clone());
-        qty.push(row.qty.clone());
-        quoteqty.push(row.quoteqty.clone());
-        commission.push(row.commission.clone());
-        commissionasset.push(row.commissionasset.clone());
-        time.push(row.time);
-        isbuyer.push(row.isbuyer);
-        ismaker.push(row.ismaker);
-        isbestmatch.push(row.isbestmatch);
+#[derive(serde::Deserialize)]
+struct Dribblets {
+    #[serde(alias = "userAssetDribblets")]
+    userassetdribblets: Vec<Dribblet>,
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct Dribblet {
+    #[serde(alias = "userAssetDribbletDetails")]
+    userassetdribbletdetails: Vec<DribbletDetail>,
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct DribbletDetail {
+    #[serde(alias = "transId")]
+    transid: u64,
+    #[serde(alias = "serviceChargeAmount")]
+    servicechargeamount: String,
+    #[serde(alias = "amount")]
+    amount: String,
+    #[serde(alias = "operateTime")]
+    operatetime: i64,
+    #[serde(alias = "transferedAmount")]
+    transferedamount: String,
+    #[serde(alias = "fromAsset")]
+    fromasset: String,
+}
+
+//
+
+/// Dust converted to BNB as buys of BNB, one row per converted asset.
+pub fn dribblet_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let dribblets: Dribblets = serde_json::from_str(response)?;
+
+    let rows = dribblets
+        .userassetdribblets
+        .into_iter()
+        .flat_map(|x| x.userassetdribbletdetails)
+        .map(|x| {
+            // BNB received is net of the service charge
+            let price = if x.transferedamount.parse::<f64>()? > 0.0 {
+                crate::decimal_div(&x.amount, &x.transferedamount, 8)?
+            } else {
+                String::from("0")
+            };
+            Ok(Trade {
+                symbol: format!("BNB{}", x.fromasset),
+                id: x.transid,
+                orderid: x.transid,
+                orderlistid: -1,
+                price,
+                qty: x.transferedamount,
+                quoteqty: x.amount,
+                commission: x.servicechargeamount,
+                commissionasset: String::from("BNB"),
+                time: x.operatetime,
+                isbuyer: true,
+                ismaker: false,
+                isbestmatch: true,
+            })
+        })
+        .collect::<Result<Vec<Trade>, Box<dyn std::error::Error>>>()?;
+
+    trades_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct FiatPayments {
+    // null without any payments
+    #[serde(default, alias = "data")]
+    data: Option<Vec<FiatPayment>>,
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct FiatPayment {
+    #[serde(alias = "orderNo")]
+    orderno: String,
+    #[serde(alias = "transactionType")]
+    transactiontype: i64,
+    #[serde(alias = "sourceAmount")]
+    sourceamount: String,
+    #[serde(alias = "fiatCurrency")]
+    fiatcurrency: String,
+    #[serde(alias = "obtainAmount")]
+    obtainamount: String,
+    #[serde(alias = "cryptoCurrency")]
+    cryptocurrency: String,
+    #[serde(alias = "totalFee")]
+    totalfee: String,
+    #[serde(alias = "price")]
+    price: String,
+    #[serde(alias = "status")]
+    status: String,
+    #[serde(alias = "createTime")]
+    createtime: i64,
+}
+
+//
+
+/// Fiat payments as trades of the crypto currency against the fiat currency, with their status.
+pub fn fiat_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let payments: FiatPayments = serde_json::from_str(response)?;
+    let payments = payments.data.unwrap_or_default();
+    let statuses = payments
+        .iter()
+        .map(|x| x.status.clone())
+        .collect::<Vec<String>>();
+
+    let rows = payments
+        .into_iter()
+        .map(|x| {
+            // order numbers are strings, ids are the leading bytes of their hash
+            let hash = sha2::Sha256::digest(x.orderno.as_bytes());
+            let id = u64::from_be_bytes(hash[..8].try_into()?);
+            let isbuyer = x.transactiontype == 0;
+            let (qty, quoteqty) = if isbuyer {
+                (x.obtainamount, x.sourceamount)
+            } else {
+                (x.sourceamount, x.obtainamount)
+            };
+            Ok(Trade {
+                symbol: format!("{}{}", x.cryptocurrency, x.fiatcurrency),
+                id,
+                orderid: id,
+                orderlistid: -1,
+                price: x.price,
+                qty,
+                quoteqty,
+                commission: x.totalfee,
+                commissionasset: x.fiatcurrency,
+                time: x.createtime,
+                isbuyer,
+                ismaker: false,
+                isbestmatch: true,
+            })
+        })
+        .collect::<Result<Vec<Trade>, Box<dyn std::error::Error>>>()?;
+
+    Ok(trades_frame(&rows)?
+        .lazy()
+        .with_column(lit(Series::new("status", statuses)))
+        .collect()?)
+}
+
+//
+
+// one record of the income ledger
+struct Income {
+    source: &'static str,
+    id: String,
+    asset: String,
+    amount: String,
+    kind: String,
+    time: i64,
+}
+
+//
+
+fn income_frame(rows: &[Income]) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    Ok(DataFrame::new(Vec::from([
+        Series::new(
+            "source",
+            rows.iter().map(|x| x.source).collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "id",
+            rows.iter().map(|x| x.id.as_str()).collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "asset",
+            rows.iter().map(|x| x.asset.as_str()).collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "amount",
+            rows.iter()
+                .map(|x| x.amount.as_str())
+                .collect::<Vec<&str>>(),
+        ),
+        Series::new(
+            "type",
+            rows.iter().map(|x| x.kind.as_str()).collect::<Vec<&str>>(),
+        ),
+        Series::new("time", rows.iter().map(|x| x.time).collect::<Vec<i64>>()),
+    ]))?)
+}
+
+//
+
+// ids are numbers or strings depending on the endpoint version
+fn id_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(x) => x.clone(),
+        serde_json::Value::Null => String::new(),
+        x => x.to_string(),
     }
+}
 
-    Ok(
-        DataFrame::new(Vec::from([
-            Series::new("symbol", symbol),
-            Series::new("id", id),
-            Series::new("orderid", orderid),
-            Series::new("orderlistid", orderlistid),
-            Series::new("price", price),
-            Series::new("qty", qty),
-            Series::new("quoteqty", quoteqty),
-            Series::new("commission", commission),
-            Series::new("commissionasset", commissionasset),
-            Series::new("time", time),
-            Series::new("isbuyer", isbuyer),
-            Series::new("ismaker", ismaker),
-            Series::new("isbestmatch", isbestmatch),
-        ]))?, // .lazy()
-              // .with_columns([
-              //     col("price").cast(DataType::Decimal(None, Some(8))),
-              //     col("qty").cast(DataType::Decimal(None, Some(8))),
-              //     col("quoteqty").cast(DataType::Decimal(None, Some(8))),
-              //     col("commission").cast(DataType::Decimal(None, Some(8))),
-              // ])
-              // .collect()?
-    )
+//
+
+#[derive(serde::Deserialize)]
+struct IncomeRows<T> {
+    // null without any records, the default does not require T: Default
+    #[serde(default = "Option::default", alias = "rows")]
+    rows: Option<Vec<T>>,
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct Dividend {
+    #[serde(alias = "tranId")]
+    tranid: serde_json::Value,
+    #[serde(alias = "amount")]
+    amount: String,
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "divTime")]
+    divtime: i64,
+    #[serde(alias = "enInfo")]
+    eninfo: String,
+}
+
+//
+
+/// Distributions such as airdrops, launchpool and staking, typed by their description.
+pub fn dividends_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let dividends: IncomeRows<Dividend> = serde_json::from_str(response)?;
+
+    let rows = dividends
+        .rows
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| Income {
+            source: "dividend",
+            id: id_string(&x.tranid),
+            asset: x.asset,
+            amount: x.amount,
+            kind: x.eninfo,
+            time: x.divtime,
+        })
+        .collect::<Vec<Income>>();
+
+    income_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct FlexibleReward {
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "rewards")]
+    rewards: String,
+    #[serde(default, alias = "projectId")]
+    projectid: serde_json::Value,
+    #[serde(alias = "type")]
+    kind: String,
+    #[serde(alias = "time")]
+    time: i64,
+}
+
+//
+
+pub fn flexible_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rewards: IncomeRows<FlexibleReward> = serde_json::from_str(response)?;
+
+    let rows = rewards
+        .rows
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| Income {
+            source: "flexible",
+            id: id_string(&x.projectid),
+            asset: x.asset,
+            amount: x.rewards,
+            kind: x.kind,
+            time: x.time,
+        })
+        .collect::<Vec<Income>>();
+
+    income_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct LockedReward {
+    #[serde(default, alias = "positionId")]
+    positionid: serde_json::Value,
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "amount")]
+    amount: String,
+    #[serde(alias = "type")]
+    kind: String,
+    #[serde(alias = "time")]
+    time: i64,
+}
+
+//
+
+pub fn locked_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rewards: IncomeRows<LockedReward> = serde_json::from_str(response)?;
+
+    let rows = rewards
+        .rows
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| Income {
+            source: "locked",
+            id: id_string(&x.positionid),
+            asset: x.asset,
+            amount: x.amount,
+            kind: x.kind,
+            time: x.time,
+        })
+        .collect::<Vec<Income>>();
+
+    income_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct EthStakingReward {
+    #[serde(alias = "time")]
+    time: i64,
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "amount")]
+    amount: String,
+    #[serde(alias = "status")]
+    status: String,
+}
+
+//
+
+/// Successful daily ETH staking rewards, paid in WBETH.
+pub fn eth_staking_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let rewards: IncomeRows<EthStakingReward> = serde_json::from_str(response)?;
+
+    let rows = rewards
+        .rows
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|x| x.status == "SUCCESS")
+        .map(|x| Income {
+            source: "eth-staking",
+            id: String::new(),
+            asset: x.asset,
+            amount: x.amount,
+            kind: String::from("ETH staking rewards"),
+            time: x.time,
+        })
+        .collect::<Vec<Income>>();
+
+    income_frame(&rows)
+}
+
+//
+
+// one record of a transfers ledger, emails are empty for own wallets
+struct Transfer {
+    source: String,
+    id: String,
+    asset: String,
+    amount: String,
+    fromemail: String,
+    fromaccount: String,
+    toemail: String,
+    toaccount: String,
+    status: String,
+    time: i64,
+}
+
+//
+
+fn transfers_frame(rows: &[Transfer]) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let strings = |f: fn(&Transfer) -> &str| rows.iter().map(f).collect::<Vec<&str>>();
+
+    Ok(DataFrame::new(Vec::from([
+        Series::new("source", strings(|x| &x.source)),
+        Series::new("id", strings(|x| &x.id)),
+        Series::new("asset", strings(|x| &x.asset)),
+        Series::new("amount", strings(|x| &x.amount)),
+        Series::new("fromemail", strings(|x| &x.fromemail)),
+        Series::new("fromaccount", strings(|x| &x.fromaccount)),
+        Series::new("toemail", strings(|x| &x.toemail)),
+        Series::new("toaccount", strings(|x| &x.toaccount)),
+        Series::new("status", strings(|x| &x.status)),
+        Series::new("time", rows.iter().map(|x| x.time).collect::<Vec<i64>>()),
+    ]))?)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct UniversalTransfer {
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "amount")]
+    amount: String,
+    #[serde(alias = "type")]
+    kind: String,
+    #[serde(alias = "status")]
+    status: String,
+    #[serde(alias = "tranId")]
+    tranid: serde_json::Value,
+    #[serde(alias = "timestamp")]
+    timestamp: i64,
+}
+
+//
+
+/// Transfers between own wallets, the universal transfer type (e.g. MAIN_FUNDING) is the source.
+pub fn universal_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let transfers: IncomeRows<UniversalTransfer> = serde_json::from_str(response)?;
+
+    let rows = transfers
+        .rows
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| {
+            // MAIN_UMFUTURE is from MAIN to UMFUTURE, PORTFOLIO_MARGIN is the only wallet with an underscore
+            let (fromaccount, toaccount) = match x.kind.strip_prefix("PORTFOLIO_MARGIN_") {
+                Some(x) => ("PORTFOLIO_MARGIN", x),
+                None => x.kind.split_once('_').unwrap_or((&x.kind, "")),
+            };
+            Transfer {
+                source: x.kind.clone(),
+                id: id_string(&x.tranid),
+                asset: x.asset,
+                amount: x.amount,
+                fromemail: String::new(),
+                fromaccount: fromaccount.to_string(),
+                toemail: String::new(),
+                toaccount: toaccount.to_string(),
+                status: x.status,
+                time: x.timestamp,
+            }
+        })
+        .collect::<Vec<Transfer>>();
+
+    transfers_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct SubUniversalTransfers {
+    // null without any transfers
+    #[serde(default, alias = "result")]
+    result: Option<Vec<SubUniversalTransfer>>,
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct SubUniversalTransfer {
+    #[serde(alias = "tranId")]
+    tranid: serde_json::Value,
+    #[serde(alias = "fromEmail")]
+    fromemail: String,
+    #[serde(alias = "toEmail")]
+    toemail: String,
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "amount")]
+    amount: String,
+    #[serde(alias = "createTimeStamp")]
+    createtimestamp: i64,
+    #[serde(alias = "fromAccountType")]
+    fromaccounttype: String,
+    #[serde(alias = "toAccountType")]
+    toaccounttype: String,
+    #[serde(alias = "status")]
+    status: String,
+}
+
+//
+
+/// Transfers of the master account from, to and between its sub-accounts.
+pub fn sub_universal_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let transfers: SubUniversalTransfers = serde_json::from_str(response)?;
+
+    let rows = transfers
+        .result
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| Transfer {
+            source: String::from("sub-account"),
+            id: id_string(&x.tranid),
+            asset: x.asset,
+            amount: x.amount,
+            fromemail: x.fromemail,
+            fromaccount: x.fromaccounttype,
+            toemail: x.toemail,
+            toaccount: x.toaccounttype,
+            status: x.status,
+            time: x.createtimestamp,
+        })
+        .collect::<Vec<Transfer>>();
+
+    transfers_frame(&rows)
+}
+
+//
+
+#[derive(serde::Deserialize)]
+struct SubHistoryTransfer {
+    #[serde(alias = "email")]
+    email: String,
+    #[serde(alias = "type")]
+    kind: i64,
+    #[serde(alias = "asset")]
+    asset: String,
+    #[serde(alias = "qty")]
+    qty: String,
+    #[serde(alias = "fromAccountType")]
+    fromaccounttype: String,
+    #[serde(alias = "toAccountType")]
+    toaccounttype: String,
+    #[serde(alias = "status")]
+    status: String,
+    #[serde(alias = "tranId")]
+    tranid: serde_json::Value,
+    #[serde(alias = "time")]
+    time: i64,
+}
+
+//
+
+/// Transfers of a sub-account, the email is the one of the counterparty.
+pub fn sub_history_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    let transfers: Vec<SubHistoryTransfer> = serde_json::from_str(response)?;
+
+    let rows = transfers
+        .into_iter()
+        .map(|x| {
+            // 1 is transfer in, 2 transfer out
+            let (fromemail, toemail) = match x.kind {
+                1 => (x.email, String::new()),
+                _ => (String::new(), x.email),
+            };
+            Transfer {
+                source: String::from("sub-account"),
+                id: id_string(&x.tranid),
+                asset: x.asset,
+                amount: x.qty,
+                fromemail,
+                fromaccount: x.fromaccounttype,
+                toemail,
+                toaccount: x.toaccounttype,
+                status: x.status,
+                time: x.time,
+            }
+        })
+        .collect::<Vec<Transfer>>();
+
+    transfers_frame(&rows)
 }
 
 //
@@ -797,7 +3053,7 @@ struct Withdrawal {
 
 //
 
-fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+pub fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
     let rows: Vec<Withdrawal> = serde_json::from_str(&response)?;
 
     let mut id: Vec<String> = Vec::new();
@@ -807,7 +3063,7 @@ fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::err
     let mut status: Vec<i64> = Vec::new();
     let mut address: Vec<String> = Vec::new();
     let mut txid: Vec<String> = Vec::new();
-    let mut applytime: Vec<u64> = Vec::new();
+    let mut applytime: Vec<i64> = Vec::new();
     let mut network: Vec<String> = Vec::new();
     let mut transfertype: Vec<i64> = Vec::new();
     let mut withdraworderid: Vec<String> = Vec::new();
@@ -815,7 +3071,7 @@ fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::err
     let mut confirmno: Vec<i64> = Vec::new();
     let mut wallettype: Vec<i64> = Vec::new();
     let mut txkey: Vec<String> = Vec::new();
-    let mut completetime: Vec<u64> = Vec::new();
+    let mut completetime: Vec<i64> = Vec::new();
 
     for row in rows.iter() {
         id.push(row.id.clone());
@@ -865,9 +3121,96 @@ fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::err
 
 //
 
-fn signature_get(request: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let key_secret = std::env::var("BINANCE_API_SECRET")?;
-    let mut key_signed = Hmac::<sha2::Sha256>::new_from_slice(key_secret.as_bytes())?;
+#[derive(serde::Deserialize)]
+struct Ticker {
+    #[serde(alias = "symbol")]
+    symbol: String,
+    #[serde(alias = "quoteVolume")]
+    quotevolume: String,
+    #[serde(alias = "count")]
+    count: u64,
+}
+
+//
+
+pub fn tickers_deserialize(
+    response: &str,
+) -> Result<HashMap<String, crate::structs::Ticker>, Box<dyn std::error::Error>> {
+    let rows: Vec<Ticker> = serde_json::from_str(response)?;
+    rows.into_iter()
+        .map(|x| {
+            Ok((
+                x.symbol,
+                crate::structs::Ticker {
+                    quote_volume: x.quotevolume.parse()?,
+                    trades: x.count,
+                },
+            ))
+        })
+        .collect()
+}
+
+//
+
+fn pair_convert(
+    pair_binance_data: Pair,
+) -> Result<crate::structs::Pair, Box<dyn std::error::Error>> {
+    let mut pair_data = crate::structs::Pair {
+        spot: pair_binance_data.spot,
+        status: pair_binance_data.status,
+        target: pair_binance_data.target,
+        target_precision: pair_binance_data.target_precision,
+        quote: pair_binance_data.quote,
+        quote_precision: pair_binance_data.quote_precision,
+        ..Default::default()
+    };
+
+    for filter in pair_binance_data.filters.iter() {
+        match filter
+            .get("filterType")
+            .ok_or("filter type not found")?
+            .as_str()
+            .ok_or("str not found")?
+        {
+            "LOT_SIZE" => {
+                pair_data.filter_quantity_min = filter
+                    .get("minQty")
+                    .ok_or("min quantity not found")?
+                    .as_str()
+                    .ok_or("minqty as_str failed")?
+                    .parse::<f64>()?;
+                pair_data.filter_quantity_max = filter
+                    .get("maxQty")
+                    .ok_or("max quantity not found")?
+                    .as_str()
+                    .ok_or("maxqty as_str failed")?
+                    .parse::<f64>()?;
+                pair_data.filter_step_size = filter
+                    .get("stepSize")
+                    .ok_or("step size not found")?
+                    .as_str()
+                    .ok_or("stepsize as_str failed")?
+                    .parse::<f64>()?;
+            }
+            "NOTIONAL" => {
+                pair_data.filter_notional_min = filter
+                    .get("minNotional")
+                    .ok_or("minnotional not found")?
+                    .as_str()
+                    .ok_or("minnotional as_str failed")?
+                    .parse::<f64>()?
+            }
+            _ => {}
+        }
+    }
+
+    Ok(pair_data)
+}
+
+//
+
+fn signature_get(secret: &Secret, request: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut key_signed = Hmac::<sha2::Sha256>::new_from_slice(secret.expose().as_bytes())?;
     key_signed.update(request.as_bytes());
     let signature = hex::encode(key_signed.finalize().into_bytes());
 
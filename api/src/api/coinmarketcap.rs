@@ -10,25 +10,32 @@ use crate::structs::AppConfig;
 //
 
 pub struct API<'a> {
-    pub client: APIClient<'a>,
+    pub client: APIClient,
+    pub config_app: &'a AppConfig,
 }
 
 //
 
 impl API<'_> {
     pub fn new(config: &AppConfig) -> Result<API, Box<dyn std::error::Error>> {
+        // only the key of the first account is used
+        let account = crate::api::accounts_get("coinmarketcap", config)?
+            .into_iter()
+            .next()
+            .ok_or("coinmarketcap account not found")?;
+        let key = crate::secrets::secret_get(&account.key)?;
+        let mut key = HeaderValue::from_str(key.expose())?;
+        key.set_sensitive(true);
         let mut headers = HeaderMap::new();
-        headers.insert(
-            HeaderName::from_static("x-cmc_pro_api_key"),
-            HeaderValue::from_str(&std::env::var("COINMARKETCAP_API_KEY")?)?,
-        );
+        headers.insert(HeaderName::from_static("x-cmc_pro_api_key"), key);
         headers.insert(
             HeaderName::from_static("accepts"),
             HeaderValue::from_static("application/json"),
         );
 
         Ok(API {
-            client: crate::api::client_get("coinmarketcap", config, headers)?,
+            client: crate::api::client_get("coinmarketcap", &account.name, config, headers)?,
+            config_app: config,
         })
     }
 
@@ -36,7 +43,7 @@ impl API<'_> {
 
     pub fn fiat_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let url = format!("{}/v1/fiat/map?limit=5000", self.client.url);
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
         let response_json: serde_json::Value = serde_json::from_str(&response)?;
         let response_data: Vec<serde_json::Value> =
             serde_json::from_value(response_json["data"].clone())?;
@@ -54,7 +61,7 @@ impl API<'_> {
             })
             .collect::<Result<HashMap<String, serde_json::Value>, Box<dyn std::error::Error>>>()?;
         info!("number of fiat: {}", fiat_data.len());
-        crate::config_write_json(&fiat_data, &crate::paths::file_fiat())?;
+        crate::config_write_json(&fiat_data, &crate::paths::file_fiat(), self.config_app)?;
 
         Ok(())
     }
@@ -66,7 +73,7 @@ impl API<'_> {
             "{}/v1/cryptocurrency/category?id=604f2753ebccdd50cd175fc1&limit=1000",
             self.client.url
         );
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
         let response_json: serde_json::Value = serde_json::from_str(&response)?;
         let response_data: Vec<serde_json::Value> =
             serde_json::from_value(response_json["data"]["coins"].clone())?;
@@ -84,7 +91,11 @@ impl API<'_> {
             })
             .collect::<Result<HashMap<String, serde_json::Value>, Box<dyn std::error::Error>>>()?;
         info!("number of stablecoins: {}", stablecoins_data.len());
-        crate::config_write_json(&stablecoins_data, &crate::paths::file_stablecoins())?;
+        crate::config_write_json(
+            &stablecoins_data,
+            &crate::paths::file_stablecoins(),
+            self.config_app,
+        )?;
 
         Ok(())
     }
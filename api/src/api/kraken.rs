@@ -1,56 +1,96 @@
 use base64::{engine::general_purpose, Engine as _};
 use hmac::{Hmac, Mac};
-use log::{debug, info};
+use log::{debug, info, warn};
 use polars::prelude::*;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use sha2::Digest;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 //
 
 use crate::structs::APIClient;
+use crate::structs::AccountConfig;
 use crate::structs::AppConfig;
+use crate::structs::Secret;
 
 //
 
 pub struct API<'a> {
     pub label: &'a str,
-    pub client: APIClient<'a>,
+    pub client: APIClient,
     pub config_app: &'a AppConfig,
     pub pairs: HashMap<String, crate::structs::Pair>,
+    pub account: AccountConfig,
+    secret: Secret,
 }
 
 //
 
 impl API<'_> {
     pub fn new(config: &AppConfig) -> Result<API, Box<dyn std::error::Error>> {
+        let account = crate::api::accounts_get("kraken", config)?
+            .into_iter()
+            .next()
+            .ok_or("kraken account not found")?;
+        let mut api = API::new_account(config, account)?;
+        api.pairs_get()?;
+
+        Ok(api)
+    }
+
+    //
+
+    /// One API per account, the pairs are requested once and shared.
+    pub fn new_accounts(config: &AppConfig) -> Result<Vec<API<'_>>, Box<dyn std::error::Error>> {
+        let mut apis: Vec<API> = Vec::new();
+        for account in crate::api::accounts_get("kraken", config)? {
+            let mut api = API::new_account(config, account)?;
+            match apis.first() {
+                Some(x) => api.pairs = x.pairs.clone(),
+                None => api.pairs_get()?,
+            }
+            apis.push(api);
+        }
+
+        Ok(apis)
+    }
+
+    //
+
+    pub fn new_account(
+        config: &AppConfig,
+        account: AccountConfig,
+    ) -> Result<API<'_>, Box<dyn std::error::Error>> {
+        let key = crate::secrets::secret_get(&account.key)?;
+        let mut key = HeaderValue::from_str(key.expose())?;
+        key.set_sensitive(true);
         let mut headers = HeaderMap::new();
-        headers.insert(
-            HeaderName::from_static("api-key"),
-            HeaderValue::from_str(&std::env::var("KRAKEN_API_KEY")?)?,
-        );
+        headers.insert(HeaderName::from_static("api-key"), key);
         let label = "kraken";
-        let mut api = API {
+        Ok(API {
             label,
-            client: crate::api::client_get(label, config, headers)?,
+            client: crate::api::client_get(label, &account.name, config, headers)?,
             config_app: config,
             pairs: HashMap::new(),
-        };
-        api.pairs_get()?;
-
-        Ok(api)
+            secret: crate::secrets::secret_get(&account.secret)?,
+            account,
+        })
     }
 
     //
 
     pub fn pairs_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let url = format!("{}/0/public/AssetPairs", self.client.url);
-        let response = crate::api::request_get(&mut self.client, crate::api::Request::Get(&url))?;
+        let response = crate::api::request_get(&self.client, crate::api::Request::Get(&url))?;
         let response_json: serde_json::Value = serde_json::from_str(&response)?;
         let pairs_data: HashMap<String, serde_json::Value> =
             serde_json::from_value(response_json["result"].clone())?;
         info!("number of pairs: {}", pairs_data.len());
-        crate::config_write_json(&pairs_data, &crate::paths::file_pairs_kraken())?;
+        crate::config_write_json(
+            &pairs_data,
+            &crate::paths::file_pairs_kraken(),
+            self.config_app,
+        )?;
 
         // TODO: deserialize pairs data
 
@@ -61,7 +101,8 @@ impl API<'_> {
     //
 
     pub fn trades_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let dir_path = crate::paths::dir_trades().join(self.label);
+        let dir_path =
+            crate::paths::dir_account(&crate::paths::dir_trades(), self.label, &self.account.name);
         debug!(
             "{} trades target directory: {}",
             self.label,
@@ -71,49 +112,54 @@ impl API<'_> {
         let file_path = dir_path.join(format!("trades.feather"));
         debug!("trades file_path is {}", file_path.as_path().display());
 
-        let mut ts_last = 0.0;
         let mut trades_previous = LazyFrame::default();
+        // start is exclusive seconds, trades of the last stored second are requested again
+        let mut ts_start = None;
         if file_path.exists() {
             trades_previous = crate::feather_read(&file_path)?;
-            ts_last = crate::column_maxu(trades_previous.clone(), "time")? as f64 / 1000.0
+            ts_start = Some(crate::column_maxi(trades_previous.clone(), "time")? / 1000 - 1);
         };
+        // the end is fixed so that offsets and the count do not move with new trades
+        let ts_end = crate::utc_ms()? / 1000 + 1;
 
-        let argument = if ts_last > 0.0 { "start" } else { "end" };
         let mut trades_new = Vec::new();
-        let mut n_trades_new: u64 = 0;
-
-        let mut batch = self.trades_batch_get(
-            argument,
-            if ts_last > 0.0 {
-                ts_last
-            } else {
-                crate::utc_ms()? as f64 / 1000.0
-            },
-        )?;
-        while batch.height() > 0 {
-            n_trades_new += batch.height() as u64;
+        let mut txids = HashSet::new();
+        let mut ofs = 0;
+        let count = loop {
+            let (batch, count) = self.trades_batch_get(ts_start, ts_end, ofs)?;
+            if batch.height() == 0 {
+                break count;
+            }
+            ofs += batch.height() as u64;
+            txids.extend(
+                batch
+                    .column("txid")?
+                    .utf8()?
+                    .into_iter()
+                    .flatten()
+                    .map(|x| x.to_string()),
+            );
             trades_new.push(
                 batch
-                    .clone()
                     .lazy()
-                    .with_column(lit(crate::utc_ms()?).alias("recorded_at")),
+                    .with_column(lit(crate::utc_ms()?).alias("recorded_at"))
+                    .with_column(lit(self.account.name.as_str()).alias("account")),
             );
-            {
-                let ts_last_new = if argument == "start" {
-                    crate::column_maxu(batch.clone().lazy(), "time")? as f64 / 1000.0
-                } else {
-                    crate::column_minu(batch.clone().lazy(), "time")? as f64 / 1000.0
-                };
-                if (ts_last == ts_last_new) & (batch.height() == 1) {
-                    break;
-                } else {
-                    ts_last = ts_last_new;
-                }
+            if ofs >= count {
+                break count;
             }
-            batch = self.trades_batch_get(argument, ts_last)?;
+        };
+        if txids.len() as u64 != count {
+            warn!(
+                "kraken trades received {} of {} between {:?} and {}",
+                txids.len(),
+                count,
+                ts_start,
+                ts_end
+            );
         }
 
-        if trades_new.len() > 0 {
+        if !trades_new.is_empty() {
             trades_new.insert(0, trades_previous);
             let mut output = concat(trades_new, true, true)?
                 .unique_stable(
@@ -123,38 +169,64 @@ impl API<'_> {
                 .sort_by_exprs([col("time"), col("txid")], [false, false], false)
                 .collect()?;
             crate::feather_write(&mut output, &file_path)?;
+
+            // stored trades of the requested range against the count of kraken
+            let n_stored = output
+                .lazy()
+                .filter(
+                    col("time")
+                        .gt(lit(ts_start.unwrap_or(i64::MIN / 1000) * 1000))
+                        .and(col("time").lt_eq(lit(ts_end * 1000))),
+                )
+                .collect()?
+                .height() as u64;
+            if n_stored != count {
+                warn!(
+                    "kraken stored {} trades between {:?} and {}, kraken counts {}",
+                    n_stored, ts_start, ts_end, count
+                );
+            }
         }
-        info!("number of new trades for kraken is {}", n_trades_new);
+        info!("number of new trades for kraken is {}", txids.len());
 
         Ok(())
     }
 
     //
 
+    /// Page of 50 trades from offset `ofs` between two times in seconds and the count of all.
     pub fn trades_batch_get(
         &mut self,
-        argument: &str,
-        ts: f64,
-    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        ts_start: Option<i64>,
+        ts_end: i64,
+        ofs: u64,
+    ) -> Result<(DataFrame, u64), Box<dyn std::error::Error>> {
         let nonce = crate::utc_ms()?;
         let uri = format!("/0/private/TradesHistory");
-        let params = format!("nonce={}&trades=false&{}={}", nonce, argument, ts);
+        let mut params = format!("nonce={}&trades=false&end={}&ofs={}", nonce, ts_end, ofs);
+        if let Some(ts_start) = ts_start {
+            params.push_str(&format!("&start={}", ts_start));
+        }
         self.signature_get(&uri, &params, nonce)?;
 
         let url = format!("{}{}", self.client.url, uri);
         let response =
-            crate::api::request_get(&mut self.client, crate::api::Request::Post((&url, &params)))?;
+            crate::api::request_get(&self.client, crate::api::Request::Post((&url, &params)))?;
+        let count = serde_json::from_str::<serde_json::Value>(&response)?["result"]["count"]
+            .as_u64()
+            .ok_or("count not found")?;
 
-        Ok(trades_deserialize(&response)?)
+        Ok((trades_deserialize(&response)?, count))
     }
 
     //
 
-
-
-
     pub fn withdrawals_get(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let dir_path = crate::paths::dir_withdrawals().join(self.label);
+        let dir_path = crate::paths::dir_account(
+            &crate::paths::dir_withdrawals(),
+            self.label,
+            &self.account.name,
+        );
         debug!(
             "{} withdrawals target directory: {}",
             self.label,
@@ -168,7 +240,7 @@ impl API<'_> {
         let mut withdrawals_previous = LazyFrame::default();
         if file_path.exists() {
             withdrawals_previous = crate::feather_read(&file_path)?;
-            ts_last = crate::column_maxu(withdrawals_previous.clone(), "time")? as f64 / 1000.0
+            ts_last = crate::column_maxi(withdrawals_previous.clone(), "time")? as f64 / 1000.0
         };
 
         let argument = if ts_last > 0.0 { "start" } else { "end" };
@@ -189,13 +261,14 @@ impl API<'_> {
                 batch
                     .clone()
                     .lazy()
-                    .with_column(lit(crate::utc_ms()?).alias("recorded_at")),
+                    .with_column(lit(crate::utc_ms()?).alias("recorded_at"))
+                    .with_column(lit(self.account.name.as_str()).alias("account")),
             );
             {
                 let ts_last_new = if argument == "start" {
-                    crate::column_maxu(batch.clone().lazy(), "time")? as f64 / 1000.0
+                    crate::column_maxi(batch.clone().lazy(), "time")? as f64 / 1000.0
                 } else {
-                    crate::column_minu(batch.clone().lazy(), "time")? as f64 / 1000.0
+                    crate::column_mini(batch.clone().lazy(), "time")? as f64 / 1000.0
                 };
                 if (ts_last == ts_last_new) & (batch.height() == 1) {
                     break;
@@ -217,7 +290,10 @@ impl API<'_> {
                 .collect()?;
             crate::feather_write(&mut output, &file_path)?;
         }
-        info!("number of new withdrawals for kraken is {}", n_withdrawals_new);
+        info!(
+            "number of new withdrawals for kraken is {}",
+            n_withdrawals_new
+        );
 
         Ok(())
     }
@@ -239,7 +315,7 @@ impl API<'_> {
 
         let url = format!("{}{}", self.client.url, uri);
         let response =
-            crate::api::request_get(&mut self.client, crate::api::Request::Post((&url, &params)))?;
+            crate::api::request_get(&self.client, crate::api::Request::Post((&url, &params)))?;
 
         Ok(withdrawals_deserialize(&response)?)
     }
@@ -250,10 +326,10 @@ impl API<'_> {
         &mut self,
         uri: &str,
         params: &str,
-        nonce: u64,
+        nonce: i64,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let key_secret = std::env::var("KRAKEN_API_SECRET")?;
-        let key_secret_decoded = general_purpose::STANDARD.decode(key_secret)?;
+        let key_secret_decoded =
+            zeroize::Zeroizing::new(general_purpose::STANDARD.decode(self.secret.expose())?);
         let mut hmac = Hmac::<sha2::Sha512>::new_from_slice(&key_secret_decoded)?;
         let params_sha2 = {
             let mut output = sha2::Sha256::default();
@@ -269,7 +345,11 @@ impl API<'_> {
         self.client.headers.insert(
             HeaderName::from_static("api-sign"),
             // "API-Sign",
-            HeaderValue::from_str(&signature)?,
+            {
+                let mut signature = HeaderValue::from_str(&signature)?;
+                signature.set_sensitive(true);
+                signature
+            },
         );
 
         Ok(())
@@ -312,7 +392,7 @@ struct Trade {
 
 //
 
-fn trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+pub fn trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
     let rows: serde_json::Value = serde_json::from_str(&response)?;
     let rows: HashMap<String, Trade> = serde_json::from_value(rows["result"]["trades"].clone())?;
 
@@ -320,7 +400,7 @@ fn trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::E
     let mut ordertxid: Vec<String> = Vec::new();
     let mut postxid: Vec<String> = Vec::new();
     let mut pair: Vec<String> = Vec::new();
-    let mut time: Vec<u64> = Vec::new();
+    let mut time: Vec<i64> = Vec::new();
     let mut r#type: Vec<String> = Vec::new();
     let mut ordertype: Vec<String> = Vec::new();
     let mut price: Vec<String> = Vec::new();
@@ -337,7 +417,7 @@ fn trades_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::E
         ordertxid.push(row.ordertxid.clone());
         postxid.push(row.postxid.clone());
         pair.push(row.pair.clone());
-        time.push((row.time * 1000.0) as u64);
+        time.push((row.time * 1000.0) as i64);
         r#type.push(row.r#type.clone());
         ordertype.push(row.ordertype.clone());
         price.push(row.price.clone());
@@ -407,14 +487,14 @@ struct Withdrawal {
 
 //
 
-fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
+pub fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
     let rows: serde_json::Value = serde_json::from_str(&response)?;
     let rows: HashMap<String, Withdrawal> =
         serde_json::from_value(rows["result"]["ledger"].clone())?;
 
     let mut ledger_id: Vec<String> = Vec::new();
     let mut refid: Vec<String> = Vec::new();
-    let mut time: Vec<u64> = Vec::new();
+    let mut time: Vec<i64> = Vec::new();
     let mut r#type: Vec<String> = Vec::new();
     let mut subtype: Vec<String> = Vec::new();
     let mut aclass: Vec<String> = Vec::new();
@@ -426,7 +506,7 @@ fn withdrawals_deserialize(response: &str) -> Result<DataFrame, Box<dyn std::err
     for (key, row) in rows.iter() {
         ledger_id.push(key.clone());
         refid.push(row.refid.clone());
-        time.push((row.time * 1000.0) as u64);
+        time.push((row.time * 1000.0) as i64);
         r#type.push(row.r#type.clone());
         subtype.push(row.subtype.clone());
         aclass.push(row.aclass.clone());
@@ -1,9 +1,15 @@
 use log::{debug, warn};
-use reqwest::blocking::Response;
+use reqwest::Response;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
 //
 
-use crate::structs::{APIClient, AppConfig};
+use crate::structs::{
+    APIClient, AccountConfig, AppConfig, Cassette, CassetteMode, SecretSource, Throttler,
+};
 
 //
 
@@ -21,40 +27,50 @@ pub enum Request<'a> {
     Post((&'a str, &'a str)),
 }
 
-fn _get(url: &str, client: &APIClient) -> Result<Response, reqwest::Error> {
+async fn _get(url: &str, client: &APIClient) -> Result<Response, reqwest::Error> {
     debug!("get - {}", url);
     client
         .client
         .get(url)
         .headers(client.headers.clone())
         .send()
+        .await
 }
 
 //
 
-fn _post(url: &str, params: &str, client: &APIClient) -> Result<Response, reqwest::Error> {
-    debug!("post - {} - {} - {:?}", url, params, client.headers);
+async fn _post(url: &str, params: &str, client: &APIClient) -> Result<Response, reqwest::Error> {
+    debug!("post - {} - {}", url, params);
     client
         .client
         .post(url)
         .headers(client.headers.clone())
         .body(params.to_string())
         .send()
+        .await
 }
 //
 
-pub fn client_get<'a>(
+pub fn client_get(
     name: &str,
-    config: &'a AppConfig,
+    account: &str,
+    config: &AppConfig,
     headers: reqwest::header::HeaderMap,
-) -> Result<APIClient<'a>, Box<dyn std::error::Error>> {
+) -> Result<APIClient, Box<dyn std::error::Error>> {
     let api_client_config = config
         .api_clients
         .get(name)
         .ok_or(format!("{} config not found", name))?;
 
+    // accounts of an exchange record their own cassettes
+    let dir_path = crate::paths::dir_cassettes().join(name).join(account);
+    if (config.cassettes.mode == CassetteMode::Replay) & !dir_path.is_dir() {
+        return Err(format!("{} {} cassettes not found", name, account).into());
+    }
+    let cassette_index = cassette_index_get(&dir_path, config.cassettes.mode)?;
+
     Ok(APIClient {
-        client: reqwest::blocking::Client::builder()
+        client: reqwest::Client::builder()
             // .default_headers(headers)
             .timeout(std::time::Duration::from_secs(
                 crate::ti_s(&config.api_timeout)? as u64,
@@ -64,69 +80,425 @@ pub fn client_get<'a>(
             ))
             .build()?,
         headers,
-        url: &api_client_config.url,
+        url: api_client_config.url.clone(),
         limit_requests: api_client_config.limit_requests,
-        limit_period: &api_client_config.limit_period,
-        limit_status_codes: &api_client_config.limit_status_codes,
+        limit_period: api_client_config.limit_period.clone(),
+        limit_status_codes: api_client_config.limit_status_codes.clone(),
         api_retries: config.api_retries,
-        throttler: Vec::new(),
+        throttler: throttler_get(&api_client_config.url)?,
+        weights: api_client_config.weights.clone(),
+        // cassettes are numbered in request order
+        concurrency: match config.cassettes.mode {
+            CassetteMode::Off => api_client_config.concurrency.max(1),
+            _ => 1,
+        },
+        label: name.to_string(),
+        account: account.to_string(),
+        cassette_mode: config.cassettes.mode,
+        cassette_index,
+        runtime: Arc::new(OnceLock::new()),
     })
 }
 //
 
+// throttlers of the run by url, clients of one host share its request weight
+static THROTTLERS: OnceLock<Mutex<HashMap<String, Throttler>>> = OnceLock::new();
+
+fn throttler_get(url: &str) -> Result<Throttler, Box<dyn std::error::Error>> {
+    Ok(THROTTLERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .map_err(|_| "throttlers poisoned")?
+        .entry(url.to_string())
+        .or_default()
+        .clone())
+}
+
+//
+
+type CassetteIndexes = HashMap<(PathBuf, CassetteMode), Arc<AtomicUsize>>;
+
+// cassette indexes of the run by directory and mode
+static CASSETTE_INDEXES: OnceLock<Mutex<CassetteIndexes>> = OnceLock::new();
+
+/// Index shared by all clients of `dir_path` in this run, a recorded directory is cleared once.
+fn cassette_index_get(
+    dir_path: &PathBuf,
+    mode: CassetteMode,
+) -> Result<Arc<AtomicUsize>, Box<dyn std::error::Error>> {
+    if mode == CassetteMode::Off {
+        return Ok(Arc::new(AtomicUsize::new(0)));
+    }
+    let mut indexes = CASSETTE_INDEXES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .map_err(|_| "cassette indexes poisoned")?;
+    let key = (std::env::current_dir()?.join(dir_path), mode);
+    if let Some(x) = indexes.get(&key) {
+        return Ok(x.clone());
+    }
+    if mode == CassetteMode::Record {
+        if dir_path.exists() {
+            std::fs::remove_dir_all(dir_path)?;
+        }
+        crate::paths::dir_create(dir_path);
+    }
+    let index = Arc::new(AtomicUsize::new(0));
+    indexes.insert(key, index.clone());
+
+    Ok(index)
+}
+
+//
+
+/// Accounts of the `name` client, a main account with `{NAME}_API_KEY` and `{NAME}_API_SECRET` without any.
+pub fn accounts_get(
+    name: &str,
+    config: &AppConfig,
+) -> Result<Vec<AccountConfig>, Box<dyn std::error::Error>> {
+    let api_client_config = config
+        .api_clients
+        .get(name)
+        .ok_or(format!("{} config not found", name))?;
+    if !api_client_config.accounts.is_empty() {
+        return Ok(api_client_config.accounts.clone());
+    }
+
+    Ok(Vec::from([AccountConfig {
+        name: "main".to_string(),
+        key: SecretSource::Env {
+            name: format!("{}_API_KEY", name.to_uppercase()),
+        },
+        secret: SecretSource::Env {
+            name: format!("{}_API_SECRET", name.to_uppercase()),
+        },
+        sub_account: false,
+    }]))
+}
+
+//
+
+/// Runs `f` for every pair on up to `concurrency` threads, each with its own clone of `api`.
+///
+/// Pairs are taken in order and no new one is started after a failure, the first failure
+/// in the order of `pairs` is returned.
+pub fn pairs_run<A, F>(
+    api: &A,
+    concurrency: usize,
+    pairs: &[String],
+    f: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    A: Clone + Sync,
+    F: Fn(&mut A, usize, &str) -> Result<(), Box<dyn std::error::Error>> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let mut results = std::thread::scope(|s| {
+        let workers = (0..concurrency.clamp(1, pairs.len().max(1)))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut api = api.clone();
+                    let mut results = Vec::new();
+                    while !failed.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(pair) = pairs.get(index) else {
+                            break;
+                        };
+                        // errors are not Send, only their message leaves the thread
+                        let result = f(&mut api, index, pair).map_err(|e| e.to_string());
+                        if result.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        results.push((index, result));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|x| x.join().map_err(|_| "worker panicked".to_string()))
+            .collect::<Result<Vec<_>, String>>()
+    })?
+    .into_iter()
+    .flatten()
+    .collect::<Vec<(usize, Result<(), String>)>>();
+    results.sort_by_key(|x| x.0);
+
+    match results.into_iter().find_map(|x| x.1.err()) {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+//
+
+/// Runs `future` on `runtime`, from async code on a thread of its own.
+///
+/// Blocking inside a runtime panics, the calling thread waits for the other one instead.
+pub fn block_on<F>(runtime: &tokio::runtime::Runtime, future: F) -> F::Output
+where
+    F: std::future::Future + Send,
+    F::Output: Send,
+{
+    if tokio::runtime::Handle::try_current().is_err() {
+        return runtime.block_on(future);
+    }
+    match std::thread::scope(|s| s.spawn(|| runtime.block_on(future)).join()) {
+        Ok(x) => x,
+        Err(e) => std::panic::resume_unwind(e),
+    }
+}
+
+//
+
+/// Blocking wrapper of [`request_get_async`], async code awaits that one instead.
 pub fn request_get(
-    client: &mut APIClient,
+    client: &APIClient,
     request: Request,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    client.throttler_sleep()?;
+    if client.runtime.get().is_none() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        // a runtime built by another clone at the same time is dropped
+        let _ = client.runtime.set(runtime);
+    }
+    block_on(
+        client.runtime.get().ok_or("runtime not found")?,
+        request_get_async(client, request),
+    )
+    .map_err(|e| e.to_string().into())
+}
+
+//
 
-    let time_start = crate::utc_ms()?;
+pub async fn request_get_async(
+    client: &APIClient,
+    request: Request<'_>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if client.cassette_mode == CassetteMode::Replay {
+        return cassette_replay(client, &request).map_err(|e| e.to_string().into());
+    }
     let url = {
         match request {
             Request::Get(url) => url,
             Request::Post(url) => url.0,
         }
     };
-    if let Ok(x) = retry::retry(
-        retry::delay::Exponential::from_millis(10)
-            .map(retry::delay::jitter)
-            .take(client.api_retries),
-        || match {
-            match request.clone() {
-                Request::Get(url) => _get(url, &client),
-                Request::Post(url) => _post(url.0, url.1, &client),
+    let ts_slot = client.throttler_sleep(client.weight(url)).await?;
+
+    let time_start = crate::utc_ms().map_err(|e| e.to_string())?;
+    let mut delays = retry::delay::Exponential::from_millis(10)
+        .map(retry::delay::jitter)
+        .take(client.api_retries);
+    let result = loop {
+        let response = match request.clone() {
+            Request::Get(url) => _get(url, client).await,
+            Request::Post(url) => _post(url.0, url.1, client).await,
+        };
+        match response {
+            Ok(response) if response.status().is_success() => break Ok(response),
+            // the key lacks the permission, another try would not change it
+            Ok(response) if response.status() == reqwest::StatusCode::UNAUTHORIZED => {
+                debug!("{}", response.text().await.unwrap_or_default());
+                break Err(REQUEST_UNAUTHORIZED);
             }
-        } {
-            Ok(response) if response.status().is_success() => retry::OperationResult::Ok(response),
             Ok(response)
                 if client
                     .limit_status_codes
                     .contains(&response.status().as_u16()) =>
             {
                 warn!("requests rate limit reached {}", url);
-                retry::OperationResult::Err("request rate limit reached")
+                break Err("request rate limit reached");
             }
             response => {
-                // TODO: deal with unwraps
-                debug!("{}", response.unwrap().text().unwrap());
-                retry::OperationResult::Retry(url)
+                match response {
+                    Ok(x) => debug!("{}", x.text().await.unwrap_or_default()),
+                    Err(e) => debug!("{}", e),
+                }
+                match delays.next() {
+                    Some(x) => tokio::time::sleep(x).await,
+                    None => break Err("request failed"),
+                }
             }
-        },
-    ) {
-        client.throttler_push()?;
-        debug!(
-            "request in {:.3}s - {:?} - {:?}",
-            crate::td(time_start)?,
-            &x.status(),
-            url,
-        );
-        Ok(x.text()?)
-    } else {
-        warn!(
-            "request failed in {:.3}s - {:?}",
-            crate::td(time_start)?,
-            url
-        );
-        Err("request failed".into())
+        }
+    };
+    client.throttler_push(ts_slot)?;
+    let td = crate::td(time_start).map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(x) => {
+            debug!("request in {:.3}s - {:?} - {:?}", td, &x.status(), url);
+            if client.cassette_mode == CassetteMode::Record {
+                let status = x.status().as_u16();
+                let response_headers = headers_redact(x.headers());
+                let response = x.text().await?;
+                cassette_record(client, &request, status, response_headers, &response)
+                    .map_err(|e| e.to_string())?;
+                return Ok(response);
+            }
+            Ok(x.text().await?)
+        }
+        Err(e) if e == REQUEST_UNAUTHORIZED => {
+            warn!("request unauthorized in {:.3}s - {:?}", td, url);
+            Err(e.into())
+        }
+        Err(_) => {
+            warn!("request failed in {:.3}s - {:?}", td, url);
+            Err("request failed".into())
+        }
     }
 }
+
+//
+
+/// Error of a request the key has no permission for, e.g. margin or futures.
+pub const REQUEST_UNAUTHORIZED: &str = "request unauthorized";
+
+/// Whether `e` is, or went through threads as the message of, [`REQUEST_UNAUTHORIZED`].
+pub fn unauthorized(e: &dyn std::error::Error) -> bool {
+    e.to_string() == REQUEST_UNAUTHORIZED
+}
+
+//
+//
+//
+//
+
+// headers carrying api keys or signatures
+const HEADERS_SECRET: [&str; 4] = ["x-mbx-apikey", "api-key", "api-sign", "x-cmc_pro_api_key"];
+
+// query parameters carrying signatures
+const PARAMS_SECRET: [&str; 1] = ["signature"];
+
+// query parameters depending on the time of the request
+const PARAMS_VOLATILE: [&str; 7] = [
+    "timestamp",
+    "signature",
+    "nonce",
+    "startTime",
+    "endTime",
+    "start",
+    "end",
+];
+
+//
+
+fn headers_redact(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(k, v)| {
+            let value = if HEADERS_SECRET.contains(&k.as_str()) {
+                "REDACTED".to_string()
+            } else {
+                v.to_str().unwrap_or_default().to_string()
+            };
+            (k.to_string(), value)
+        })
+        .collect()
+}
+
+//
+
+fn params_redact(params: &str) -> String {
+    params
+        .split('&')
+        .map(|x| match x.split_once('=') {
+            Some((k, _)) if PARAMS_SECRET.contains(&k) => format!("{}=REDACTED", k),
+            _ => x.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+//
+
+fn cassette_request(client: &APIClient, request: &Request) -> (String, String, String) {
+    let (method, url, body) = match request {
+        Request::Get(url) => ("GET", *url, ""),
+        Request::Post((url, params)) => ("POST", *url, *params),
+    };
+    // urls are stored without the client url so cassettes replay against any host
+    let url = url.strip_prefix(&client.url).unwrap_or(url);
+    let url = match url.split_once('?') {
+        Some((path, params)) => format!("{}?{}", path, params_redact(params)),
+        None => url.to_string(),
+    };
+
+    (method.to_string(), url, params_redact(body))
+}
+
+//
+
+fn cassette_record(
+    client: &APIClient,
+    request: &Request,
+    status: u16,
+    response_headers: BTreeMap<String, String>,
+    response: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (method, url, body) = cassette_request(client, request);
+    let cassette = Cassette {
+        method,
+        url,
+        body,
+        headers: headers_redact(&client.headers),
+        status,
+        response_headers,
+        response: response.to_string(),
+    };
+    // clones of the client share the index
+    let index = client.cassette_index.fetch_add(1, Ordering::SeqCst);
+    let file_path = crate::paths::dir_cassettes()
+        .join(&client.label)
+        .join(&client.account)
+        .join(format!("{:06}.json", index));
+    crate::json_write(&cassette, &file_path)?;
+
+    Ok(())
+}
+
+//
+
+fn cassette_replay(
+    client: &APIClient,
+    request: &Request,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let index = client.cassette_index.load(Ordering::SeqCst);
+    let file_path = crate::paths::dir_cassettes()
+        .join(&client.label)
+        .join(&client.account)
+        .join(format!("{:06}.json", index));
+    if !file_path.is_file() {
+        return Err(format!("cassette {} not found", file_path.as_path().display()).into());
+    }
+    let cassette: Cassette = crate::json_read(&file_path)?;
+
+    // timestamps and nonces differ between runs, cassettes are served in recorded order
+    let (method, url, body) = cassette_request(client, request);
+    let stable = |url: &str, body: &str| {
+        let (path, params) = url.split_once('?').unwrap_or((url, ""));
+        let params = params
+            .split('&')
+            .chain(body.split('&'))
+            .filter(|x| !PARAMS_VOLATILE.contains(&x.split('=').next().unwrap_or_default()))
+            .collect::<Vec<&str>>()
+            .join("&");
+        format!("{}?{}", path, params)
+    };
+    if (cassette.method != method) | (stable(&cassette.url, &cassette.body) != stable(&url, &body))
+    {
+        return Err(format!(
+            "cassette {} is {} {}, requested {} {}",
+            index, cassette.method, cassette.url, method, url
+        )
+        .into());
+    }
+    debug!("replay {} - {} {}", index, method, url);
+    client.cassette_index.fetch_add(1, Ordering::SeqCst);
+
+    Ok(cassette.response)
+}
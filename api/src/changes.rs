@@ -0,0 +1,309 @@
+use log::debug;
+
+//
+//
+//
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Listed,
+    Delisted,
+    Status,
+    Filter,
+    Added,
+    Removed,
+    Changed,
+}
+
+//
+
+/// One entry of the timeline built from `logs/{kind}/{timestamp}.json` diffs.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Change {
+    pub ts: i64,
+    pub kind: String,
+    pub symbol: String,
+    pub change: ChangeKind,
+    pub path: String,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+}
+
+//
+
+#[derive(Clone, Debug, Default)]
+pub struct ChangesFilter {
+    pub kinds: Vec<String>,
+    pub ts_from: Option<i64>,
+    pub ts_to: Option<i64>,
+    pub symbol: Option<String>,
+}
+
+//
+//
+//
+
+pub fn changes_get(
+    filter: &ChangesFilter,
+    identity_keys: &[String],
+) -> Result<Vec<Change>, Box<dyn std::error::Error>> {
+    let mut changes = Vec::new();
+
+    for dir_kind in crate::paths::dir_list(&crate::paths::dir_logs())? {
+        if !dir_kind.path.is_dir() {
+            continue;
+        }
+        let kind = dir_kind.stem;
+        if !filter.kinds.is_empty() & !filter.kinds.contains(&kind) {
+            continue;
+        }
+        // the current data guesses array or scalar for logs that did not record it
+        let file_data = crate::paths::dir_config().join(format!("{kind}.json"));
+        let data: serde_json::Value = if file_data.is_file() {
+            crate::json_read(&file_data)?
+        } else {
+            serde_json::Value::Null
+        };
+
+        for file_log in crate::paths::dir_list(&dir_kind.path)? {
+            if file_log.extension != "json" {
+                continue;
+            }
+            let ts = crate::date_to_unix_ms(&file_log.stem)?;
+            if filter.ts_from.is_some_and(|x| ts < x) | filter.ts_to.is_some_and(|x| ts > x) {
+                continue;
+            }
+            debug!("changes read - {}", file_log.path.as_path().display());
+            let diff: crate::diff::Diff = crate::json_read(&file_log.path)?;
+            changes.extend(
+                diff_changes_get(&diff, ts, &kind, &data, identity_keys)
+                    .into_iter()
+                    .filter(|x| filter.symbol.as_ref().is_none_or(|s| x.symbol == *s)),
+            );
+        }
+    }
+    changes.sort_by(|a, b| {
+        (a.ts, &a.kind, &a.symbol, &a.path).cmp(&(b.ts, &b.kind, &b.symbol, &b.path))
+    });
+
+    Ok(changes)
+}
+
+//
+
+pub fn diff_changes_get(
+    diff: &crate::diff::Diff,
+    ts: i64,
+    kind: &str,
+    data: &serde_json::Value,
+    identity_keys: &[String],
+) -> Vec<Change> {
+    let change_get = |path: &str, change: ChangeKind, old: Vec<String>, new: Vec<String>| Change {
+        ts,
+        kind: kind.to_string(),
+        symbol: path_segments(path)
+            .first()
+            .map(|x| x.to_string())
+            .unwrap_or_default(),
+        change,
+        path: path.to_string(),
+        old,
+        new,
+    };
+    let mut changes = Vec::new();
+
+    for (path, keys) in diff.new.iter() {
+        for key in keys {
+            changes.push(if path.is_empty() {
+                change_get(
+                    &format!("/{key}"),
+                    ChangeKind::Listed,
+                    Vec::new(),
+                    Vec::new(),
+                )
+            } else {
+                change_get(
+                    path,
+                    path_kind(path, ChangeKind::Added),
+                    Vec::new(),
+                    Vec::from([key.clone()]),
+                )
+            });
+        }
+    }
+    for (path, keys) in diff.old.iter() {
+        for key in keys {
+            changes.push(if path.is_empty() {
+                change_get(
+                    &format!("/{key}"),
+                    ChangeKind::Delisted,
+                    Vec::new(),
+                    Vec::new(),
+                )
+            } else {
+                change_get(
+                    path,
+                    path_kind(path, ChangeKind::Removed),
+                    Vec::from([key.clone()]),
+                    Vec::new(),
+                )
+            });
+        }
+    }
+    for (path, [a, b]) in diff.diff.iter() {
+        // arrays are logged as [new, old], scalars as [old, new]
+        let value = diff.values.get(path).copied().unwrap_or_else(|| {
+            // older logs did not record it, the current data is the best guess
+            if value_get(data, path, identity_keys).is_some_and(|x| x.is_array()) {
+                crate::diff::DiffValue::Array
+            } else {
+                crate::diff::DiffValue::Scalar
+            }
+        });
+        let (old, new) = if value == crate::diff::DiffValue::Array {
+            (b.clone(), a.clone())
+        } else {
+            (a.clone(), b.clone())
+        };
+        changes.push(change_get(
+            path,
+            path_kind(path, ChangeKind::Changed),
+            old,
+            new,
+        ));
+    }
+
+    changes
+}
+
+//
+
+fn path_kind(path: &str, change: ChangeKind) -> ChangeKind {
+    if path.ends_with("/status") {
+        ChangeKind::Status
+    } else if path.contains("/filters") {
+        ChangeKind::Filter
+    } else {
+        change
+    }
+}
+
+//
+
+// "/BTCUSDT/filters[LOT_SIZE]/stepSize" -> ["BTCUSDT", "filters", "[LOT_SIZE]", "stepSize"]
+fn path_segments(path: &str) -> Vec<String> {
+    let mut segments: Vec<String> = Vec::new();
+    for c in path.chars() {
+        match c {
+            '/' => segments.push(String::new()),
+            '[' => segments.push(String::from("[")),
+            _ => match segments.last_mut() {
+                Some(x) => x.push(c),
+                None => segments.push(c.to_string()),
+            },
+        }
+    }
+    segments
+}
+
+//
+
+fn value_get<'a>(
+    data: &'a serde_json::Value,
+    path: &str,
+    identity_keys: &[String],
+) -> Option<&'a serde_json::Value> {
+    path_segments(path).iter().try_fold(data, |value, segment| {
+        match segment.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            Some(identity) => value.as_array()?.iter().find(|x| {
+                identity_keys.iter().any(|key| match x.get(key) {
+                    Some(serde_json::Value::String(s)) => s == identity,
+                    Some(serde_json::Value::Number(n)) => n.to_string() == identity,
+                    _ => false,
+                })
+            }),
+            None => value.get(segment),
+        }
+    })
+}
+
+//
+//
+//
+
+// logged values are JSON, strings are shown without quotes
+fn value_show(value: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(value) {
+        Ok(serde_json::Value::String(x)) => x,
+        _ => value.to_string(),
+    }
+}
+
+//
+
+fn kind_noun(kind: &str) -> &str {
+    match kind {
+        x if x.starts_with("pairs") => "pair",
+        "fiat" => "fiat",
+        "stablecoins" => "stablecoin",
+        _ => "entry",
+    }
+}
+
+//
+
+pub fn changes_markdown(
+    changes: &[Change],
+    timezone: chrono_tz::Tz,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut lines = Vec::from([String::from("# Changes")]);
+    let mut groups: Vec<((i64, &str), Vec<&Change>)> = Vec::new();
+    for change in changes {
+        match groups.last_mut() {
+            Some((key, x)) if *key == (change.ts, change.kind.as_str()) => x.push(change),
+            _ => groups.push(((change.ts, &change.kind), Vec::from([change]))),
+        }
+    }
+
+    for ((ts, kind), group) in groups {
+        lines.push(String::new());
+        lines.push(format!(
+            "## {} {}",
+            crate::unix_ms_to_string_tz(ts, timezone)?,
+            kind
+        ));
+        let noun = kind_noun(kind);
+        for change in group {
+            let values = |x: &Vec<String>| {
+                x.iter()
+                    .map(|x| value_show(x))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            let path = change.path.trim_start_matches('/');
+            lines.push(match change.change {
+                ChangeKind::Listed => format!("- new {}: {}", noun, change.symbol),
+                ChangeKind::Delisted => format!("- removed {}: {}", noun, change.symbol),
+                _ if change.old.is_empty() => format!("- {}: added {}", path, values(&change.new)),
+                _ if change.new.is_empty() => {
+                    format!("- {}: removed {}", path, values(&change.old))
+                }
+                _ => format!(
+                    "- {}: {} → {}",
+                    path,
+                    values(&change.old),
+                    values(&change.new)
+                ),
+            });
+        }
+    }
+
+    Ok(lines.join("\n"))
+}
+
+//
+
+pub fn changes_json(changes: &[Change]) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(serde_json::to_string_pretty(changes)?)
+}
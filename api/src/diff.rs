@@ -10,11 +10,24 @@ pub enum StringOrVec {
     Vec(Vec<String>),
 }
 
-#[derive(Debug, serde::Serialize)]
+/// What a `diff` entry compared, arrays are logged as [new, old] and scalars as [old, new].
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffValue {
+    Array,
+    Scalar,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Diff {
     pub new: HashMap<String, Vec<String>>,
     pub old: HashMap<String, Vec<String>>,
     pub diff: HashMap<String, [Vec<String>; 2]>,
+    // logs written before the values were recorded have none
+    #[serde(default)]
+    pub values: HashMap<String, DiffValue>,
+    #[serde(skip)]
+    pub identity_keys: Vec<String>,
 }
 impl Diff {
     pub fn new() -> Self {
@@ -22,6 +35,15 @@ impl Diff {
             new: HashMap::new(),
             old: HashMap::new(),
             diff: HashMap::new(),
+            values: HashMap::new(),
+            identity_keys: Vec::new(),
+        }
+    }
+    /// Arrays of objects sharing one of `identity_keys` are matched element by element on it.
+    pub fn keyed(identity_keys: &[String]) -> Self {
+        Self {
+            identity_keys: identity_keys.to_vec(),
+            ..Self::new()
         }
     }
 }
@@ -29,40 +51,20 @@ impl Diff {
 pub fn run_json(left: &serde_json::Value, right: &serde_json::Value, path: &str, diff: &mut Diff) -> Result<(), Box<dyn std::error::Error>> {
     match left {
         left if left.is_object() & right.is_object() => {
-            let keys_left = &map_keys_get(left)?;
-            let keys_right = &map_keys_get(right)?;
-
-            let keys_new = keys_left
-                .iter()
-                .filter(|x| !keys_right.contains(x))
-                .map(|x| x.to_string().clone())
-                .collect::<Vec<String>>();
-            let keys_old = keys_right
-                .iter()
-                .filter(|x| !keys_left.contains(x))
-                .map(|x| x.to_string().clone())
-                .collect::<Vec<String>>();
-            let keys_common = keys_left
-                .iter()
-                .filter(|x| keys_right.contains(x))
-                .map(|x| *x)
-                .collect::<Vec<&String>>();
-
-            if !keys_new.is_empty() {
-                diff.new.insert(path.to_string(), keys_new);
-            }
-            if !keys_old.is_empty() {
-                diff.old.insert(path.to_string(), keys_old);
-            }
-
-            for key in keys_common.iter() {
-                run_json(&left[key], &right[key], &format!("{}/{}", path, key), diff)?
-            }
+            let map_left = left.as_object().ok_or("map not found")?;
+            let map_right = right.as_object().ok_or("map not found")?;
+            run_map(map_left, map_right, path, |path, key| format!("{}/{}", path, key), diff)?
         }
         left if left.is_array() & right.is_array() => {
             let arr_left = arr_elements_get(left)?;
             let arr_right = arr_elements_get(right)?;
 
+            if let Some(key) = identity_key_get(arr_left, arr_right, &diff.identity_keys) {
+                let map_left = arr_keyed_get(arr_left, &key)?;
+                let map_right = arr_keyed_get(arr_right, &key)?;
+                return run_map(&map_left, &map_right, path, |path, key| format!("{}[{}]", path, key), diff);
+            }
+
             let elements_new = arr_left
                 .iter()
                 .filter(|x| !arr_right.contains(x))
@@ -76,6 +78,7 @@ pub fn run_json(left: &serde_json::Value, right: &serde_json::Value, path: &str,
 
             if !elements_new.is_empty() | !elements_old.is_empty() {
                 diff.diff.insert(path.to_string(), [elements_new, elements_old]);
+                diff.values.insert(path.to_string(), DiffValue::Array);
             }
         }
         left => {
@@ -87,6 +90,7 @@ pub fn run_json(left: &serde_json::Value, right: &serde_json::Value, path: &str,
                         Vec::from([serde_json::to_string(left)?]),
                     ],
                 );
+                diff.values.insert(path.to_string(), DiffValue::Scalar);
             }
         }
     }
@@ -94,11 +98,74 @@ pub fn run_json(left: &serde_json::Value, right: &serde_json::Value, path: &str,
     Ok({})
 }
 
-fn map_keys_get(map: &serde_json::Value) -> Result<Vec<&String>, Box<dyn std::error::Error>> {
-    Ok(map.as_object()
-        .ok_or("map not found")?
-        .keys()
-        .collect::<Vec<&String>>())
+fn run_map(
+    left: &serde_json::Map<String, serde_json::Value>,
+    right: &serde_json::Map<String, serde_json::Value>,
+    path: &str,
+    path_key: fn(&str, &str) -> String,
+    diff: &mut Diff,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let keys_left = left.keys().collect::<Vec<&String>>();
+    let keys_right = right.keys().collect::<Vec<&String>>();
+
+    let keys_new = keys_left
+        .iter()
+        .filter(|x| !keys_right.contains(x))
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>();
+    let keys_old = keys_right
+        .iter()
+        .filter(|x| !keys_left.contains(x))
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>();
+    let keys_common = keys_left
+        .iter()
+        .filter(|x| keys_right.contains(x))
+        .copied()
+        .collect::<Vec<&String>>();
+
+    if !keys_new.is_empty() {
+        diff.new.insert(path.to_string(), keys_new);
+    }
+    if !keys_old.is_empty() {
+        diff.old.insert(path.to_string(), keys_old);
+    }
+
+    for key in keys_common.iter() {
+        run_json(&left[*key], &right[*key], &path_key(path, key), diff)?
+    }
+
+    Ok(())
+}
+
+fn identity_get(element: &serde_json::Value, key: &str) -> Option<String> {
+    match element.as_object()?.get(key)? {
+        serde_json::Value::String(x) => Some(x.clone()),
+        serde_json::Value::Number(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+// the first identity key present and unique in all elements of both arrays
+fn identity_key_get(left: &[serde_json::Value], right: &[serde_json::Value], identity_keys: &[String]) -> Option<String> {
+    if left.is_empty() & right.is_empty() {
+        return None;
+    }
+    identity_keys
+        .iter()
+        .find(|key| {
+            [left, right].iter().all(|arr| {
+                let identities = arr.iter().map(|x| identity_get(x, key)).collect::<Option<Vec<String>>>();
+                identities.is_some_and(|x| x.iter().collect::<std::collections::HashSet<&String>>().len() == x.len())
+            })
+        })
+        .cloned()
+}
+
+fn arr_keyed_get(arr: &[serde_json::Value], key: &str) -> Result<serde_json::Map<String, serde_json::Value>, Box<dyn std::error::Error>> {
+    arr.iter()
+        .map(|x| Ok((identity_get(x, key).ok_or("identity not found")?, x.clone())))
+        .collect()
 }
 
 fn arr_elements_get(arr: &serde_json::Value) -> Result<&Vec<serde_json::Value>, Box<dyn std::error::Error>> {
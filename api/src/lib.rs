@@ -1,5 +1,5 @@
-use chrono::NaiveDateTime;
-use log::debug;
+use chrono::{NaiveDateTime, TimeZone};
+use log::{debug, info};
 use polars::prelude::*;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -7,9 +7,14 @@ use std::path::PathBuf;
 //
 
 pub mod api;
+pub mod changes;
 pub mod diff;
+pub mod notify;
 pub mod paths;
+pub mod secrets;
+pub mod snapshots;
 pub mod structs;
+pub mod universe;
 
 //
 //
@@ -119,6 +124,7 @@ where
 pub fn config_write_json(
     data: &HashMap<String, serde_json::Value>,
     file_data: &PathBuf,
+    config: &crate::structs::AppConfig,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let kind = file_data
         .file_stem()
@@ -130,10 +136,11 @@ pub fn config_write_json(
         .ok_or("extension not found")?
         .to_str()
         .ok_or("string not found")?;
-    let timestamp = unix_s_to_string(utc_s()?)?;
+    let ts = utc_ms()?;
+    let timestamp = unix_ms_to_string(ts)?;
+    let data_new = serde_json::to_value(data)?;
     if file_data.is_file() {
-        let mut diff = crate::diff::Diff::new();
-        let data_new = serde_json::to_value(data)?;
+        let mut diff = crate::diff::Diff::keyed(&config.diff.identity_keys);
         let data_old: serde_json::Value = json_read(file_data)?;
         crate::diff::run_json(&data_new, &data_old, "", &mut diff)?;
         if !(diff.new.is_empty() & diff.old.is_empty() & diff.diff.is_empty()) {
@@ -142,10 +149,13 @@ pub fn config_write_json(
             let file_log_diff = &dir_log.join(format!("{}.{}", timestamp, extension));
             json_write(&diff, file_log_diff)?;
             json_write(data, file_data)?;
+            crate::notify::notify(config, kind, ts, &diff, &data_new, &data_old)?;
         }
     } else {
         json_write(data, file_data)?;
     }
+    // diffs cannot be replayed backwards, full snapshots keep every version
+    crate::snapshots::snapshot_write(kind, ts, &data_new)?;
 
     Ok({})
 }
@@ -160,108 +170,255 @@ fn utc() -> Result<std::time::Duration, Box<dyn std::error::Error>> {
 
 //
 
-pub fn utc_ms() -> Result<u64, Box<dyn std::error::Error>> {
-    Ok(utc()?.as_millis() as u64)
+pub fn utc_ms() -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(utc()?.as_millis().try_into()?)
 }
 
+//
+//
 //
 
-pub fn utc_s() -> Result<u32, Box<dyn std::error::Error>> {
-    Ok(utc()?.as_secs() as u32)
+pub fn timezone_parse(timezone: &str) -> Result<chrono_tz::Tz, Box<dyn std::error::Error>> {
+    Ok(timezone.parse::<chrono_tz::Tz>()?)
 }
 
 //
-//
+
+pub fn date_to_unix_ms(datetime_string: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    date_to_unix_ms_tz(datetime_string, chrono_tz::UTC)
+}
+
 //
 
-pub fn date_to_unix_s(datetime_string: &str) -> Result<u32, Box<dyn std::error::Error>> {
-    Ok(if datetime_string.chars().count() == 10 {
+pub fn date_to_unix_ms_tz(
+    datetime_string: &str,
+    timezone: chrono_tz::Tz,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    // RFC3339 strings carry their own offset, the rest is local time of the timezone
+    if let Ok(x) = chrono::DateTime::parse_from_rfc3339(datetime_string) {
+        return Ok(x.timestamp_millis());
+    }
+    let datetime = if datetime_string.chars().count() == 10 {
         NaiveDateTime::parse_from_str(
             &format!("{} 00:00:00", datetime_string),
             "%Y-%m-%d %H:%M:%S",
         )?
-        .timestamp() as u32
     } else {
-        NaiveDateTime::parse_from_str(datetime_string, "%Y-%m-%d %H:%M:%S")?.timestamp() as u32
-    })
+        NaiveDateTime::parse_from_str(datetime_string, "%Y-%m-%d %H:%M:%S")?
+    };
+    time_to_unix_ms_tz(datetime, timezone)
 }
 
 //
 
-pub fn date_to_unix_ms(datetime_string: &str) -> Result<u64, Box<dyn std::error::Error>> {
-    Ok(if datetime_string.chars().count() == 10 {
-        NaiveDateTime::parse_from_str(
-            &format!("{} 00:00:00", datetime_string),
-            "%Y-%m-%d %H:%M:%S",
-        )?
-        .timestamp_millis() as u64
-    } else {
-        NaiveDateTime::parse_from_str(datetime_string, "%Y-%m-%d %H:%M:%S")?.timestamp_millis()
-            as u64
-    })
+pub fn time_to_unix_ms_tz(
+    datetime: NaiveDateTime,
+    timezone: chrono_tz::Tz,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    // ambiguous local times (DST end) resolve to the earlier instant
+    Ok(timezone
+        .from_local_datetime(&datetime)
+        .earliest()
+        .ok_or(format!("{} does not exist in {}", datetime, timezone))?
+        .timestamp_millis())
 }
 
 //
-pub fn time_to_unix_s(datetime: NaiveDateTime) -> u32 {
-    datetime.timestamp() as u32
+
+pub fn time_to_unix_ms(datetime: NaiveDateTime) -> i64 {
+    datetime.and_utc().timestamp_millis()
 }
 
 //
 
-pub fn time_to_unix_ms(datetime: NaiveDateTime) -> u64 {
-    datetime.timestamp_millis() as u64
+pub fn unix_ms_to_time(ts: i64) -> Result<NaiveDateTime, Box<dyn std::error::Error>> {
+    Ok(chrono::DateTime::from_timestamp_millis(ts)
+        .ok_or("datetime failed")?
+        .naive_utc())
 }
 
 //
 
-pub fn unix_ms_to_time(ts: u64) -> Result<NaiveDateTime, Box<dyn std::error::Error>> {
-    Ok(NaiveDateTime::from_timestamp_millis(ts as i64).ok_or("datetime failed")?)
+pub fn unix_ms_to_string(ts: i64) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(unix_ms_to_time(ts)?.format("%Y-%m-%d %H:%M:%S").to_string())
 }
 
 //
 
-pub fn unix_s_to_time(ts: u32) -> Result<NaiveDateTime, Box<dyn std::error::Error>> {
-    Ok(NaiveDateTime::from_timestamp_opt(ts as i64, 0).ok_or("datetime failed")?)
+pub fn unix_ms_to_string_tz(
+    ts: i64,
+    timezone: chrono_tz::Tz,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(chrono::DateTime::from_timestamp_millis(ts)
+        .ok_or("datetime failed")?
+        .with_timezone(&timezone)
+        .to_rfc3339())
 }
 
 //
 
-pub fn unix_ms_to_string(ts: u64) -> Result<String, Box<dyn std::error::Error>> {
-    Ok(unix_ms_to_time(ts)?.format("%Y-%m-%d %H:%M:%S").to_string())
+pub fn day_bounds_ms(
+    ts: i64,
+    timezone: chrono_tz::Tz,
+) -> Result<(i64, i64), Box<dyn std::error::Error>> {
+    let date = chrono::DateTime::from_timestamp_millis(ts)
+        .ok_or("datetime failed")?
+        .with_timezone(&timezone)
+        .date_naive();
+    let date_next = date.succ_opt().ok_or("date failed")?;
+    // days are 23 or 25 hours long when DST changes
+    Ok((
+        time_to_unix_ms_tz(date.and_time(chrono::NaiveTime::MIN), timezone)?,
+        time_to_unix_ms_tz(date_next.and_time(chrono::NaiveTime::MIN), timezone)?,
+    ))
 }
 
 //
 
-pub fn unix_s_to_string(ts: u32) -> Result<String, Box<dyn std::error::Error>> {
-    Ok(unix_s_to_time(ts)?.format("%Y-%m-%d %H:%M:%S").to_string())
+pub fn year_bounds_ms(
+    year: i32,
+    timezone: chrono_tz::Tz,
+) -> Result<(i64, i64), Box<dyn std::error::Error>> {
+    let start = chrono::NaiveDate::from_ymd_opt(year, 1, 1).ok_or("date failed")?;
+    let end = chrono::NaiveDate::from_ymd_opt(year + 1, 1, 1).ok_or("date failed")?;
+    Ok((
+        time_to_unix_ms_tz(start.and_time(chrono::NaiveTime::MIN), timezone)?,
+        time_to_unix_ms_tz(end.and_time(chrono::NaiveTime::MIN), timezone)?,
+    ))
 }
 
 //
 
-pub fn ti_s(interval: &str) -> Result<u32, Box<dyn std::error::Error>> {
-    let period: &str = &interval[interval.len() - 1..];
-    assert!(["s", "m", "h", "d", "w"].contains(&period));
-    let length: u32 = interval.replace(&period, "").parse()?;
-    Ok(match period {
-        "s" => Some(length),
-        "m" => Some(60 * length),
-        "h" => Some(3_600 * length),
-        "d" => Some(86_400 * length),
-        "w" => Some(604_800 * length),
+pub fn interval_parse(interval: &str) -> Result<(u32, char), Box<dyn std::error::Error>> {
+    let period = interval.chars().last().ok_or("interval is empty")?;
+    // "m" is minutes and "M" is calendar months
+    if !['s', 'm', 'h', 'd', 'w', 'M', 'y'].contains(&period) {
+        return Err(format!("interval {} has unknown period", interval).into());
+    }
+    let length: u32 = interval[..interval.len() - period.len_utf8()]
+        .parse()
+        .or(Err(format!("interval {} has invalid length", interval)))?;
+    if length == 0 {
+        return Err(format!("interval {} has zero length", interval).into());
+    }
+
+    Ok((length, period))
+}
+
+//
+
+pub fn interval_months(interval: &str) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+    Ok(match interval_parse(interval)? {
+        (length, 'M') => Some(length),
+        (length, 'y') => Some(12 * length),
         _ => None,
+    })
+}
+
+//
+
+pub fn ti_s(interval: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    let (length, period) = interval_parse(interval)?;
+    let seconds: u32 = match period {
+        's' => 1,
+        'm' => 60,
+        'h' => 3_600,
+        'd' => 86_400,
+        'w' => 604_800,
+        _ => return Err(format!("interval {} has no fixed length", interval).into()),
+    };
+    Ok(seconds
+        .checked_mul(length)
+        .ok_or(format!("interval {} is too long", interval))?)
+}
+
+//
+
+pub fn ti_ms(interval: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(1000 * ti_s(interval)? as i64)
+}
+
+//
+
+pub fn interval_add_ms(ts: i64, interval: &str, n: u32) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(if let Some(months) = interval_months(interval)? {
+        // added to the start each time, so that month ends are not clamped repeatedly
+        time_to_unix_ms(
+            unix_ms_to_time(ts)?
+                .checked_add_months(chrono::Months::new(months * n))
+                .ok_or("datetime failed")?,
+        )
+    } else {
+        ti_ms(interval)?
+            .checked_mul(n as i64)
+            .and_then(|x| x.checked_add(ts))
+            .ok_or("timestamp overflow")?
+    })
+}
+
+//
+
+// mantissa and number of decimals of a decimal string
+fn decimal_parse(x: &str) -> Result<(i128, usize), Box<dyn std::error::Error>> {
+    let (int, frac) = x.trim().split_once('.').unwrap_or((x.trim(), ""));
+    Ok((format!("{}{}", int, frac).parse::<i128>()?, frac.len()))
+}
+
+fn decimal_format(mantissa: i128, scale: usize) -> String {
+    let digits = format!("{:0>width$}", mantissa.unsigned_abs(), width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    let sign = if mantissa < 0 { "-" } else { "" };
+
+    match scale {
+        0 => format!("{}{}", sign, int),
+        _ => format!("{}{}.{}", sign, int, frac),
     }
-    .ok_or("period not found")?)
 }
 
 //
 
-pub fn ti_ms(interval: &str) -> Result<u64, Box<dyn std::error::Error>> {
-    Ok(1000 * ti_s(interval)? as u64)
+/// Exact product of two decimal strings, with as many decimals as both factors together.
+pub fn decimal_mul(a: &str, b: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let ((a, scale_a), (b, scale_b)) = (decimal_parse(a)?, decimal_parse(b)?);
+    let product = a.checked_mul(b).ok_or("decimal overflow")?;
+
+    Ok(decimal_format(product, scale_a + scale_b))
+}
+
+//
+
+/// Quotient of two decimal strings with `scale` decimals, rounded half away from zero.
+pub fn decimal_div(a: &str, b: &str, scale: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let ((a, scale_a), (b, scale_b)) = (decimal_parse(a)?, decimal_parse(b)?);
+    if b == 0 {
+        return Err("decimal division by zero".into());
+    }
+    // a / 10^scale_a / (b / 10^scale_b) * 10^scale, shifted to integers
+    let shift = |x: i128, n: usize| -> Result<i128, Box<dyn std::error::Error>> {
+        Ok(
+            x.checked_mul(10_i128.checked_pow(n as u32).ok_or("decimal overflow")?)
+                .ok_or("decimal overflow")?,
+        )
+    };
+    let (numerator, denominator) = if scale + scale_b >= scale_a {
+        (shift(a, scale + scale_b - scale_a)?, b)
+    } else {
+        (a, shift(b, scale_a - scale - scale_b)?)
+    };
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    let quotient = if 2 * remainder.abs() >= denominator.abs() {
+        quotient + numerator.signum() * denominator.signum()
+    } else {
+        quotient
+    };
+
+    Ok(decimal_format(quotient, scale))
 }
 
 //
 
-pub fn td(time_start: u64) -> Result<f32, Box<dyn std::error::Error>> {
+pub fn td(time_start: i64) -> Result<f32, Box<dyn std::error::Error>> {
     Ok((utc_ms()? - time_start) as f32 / 1_000.0)
 }
 
@@ -297,54 +454,580 @@ pub fn column_minu(lf: LazyFrame, column_name: &str) -> Result<u64, Box<dyn std:
 
 //
 
-pub fn pairs_mature(exchange: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut pairs = Vec::new();
-    let mut tss = Vec::new();
+pub fn column_maxi(lf: LazyFrame, column_name: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(lf
+        .select([col(column_name).cast(DataType::Int64).max()])
+        .collect()?
+        .column(column_name)?
+        .max::<i64>()
+        .ok_or("max not found")?)
+}
+
+//
 
-    for dir_entry in crate::paths::dir_list(&crate::paths::dir_klines().join(exchange))? {
-        if dir_entry.extension == "feather" {
-            pairs.push(dir_entry.stem);
-            tss.push(column_minu(feather_read(&dir_entry.path)?, "ts")?);
+pub fn column_mini(lf: LazyFrame, column_name: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(lf
+        .select([col(column_name).cast(DataType::Int64).min()])
+        .collect()?
+        .column(column_name)?
+        .min::<i64>()
+        .ok_or("min not found")?)
+}
+
+//
+
+pub fn column_values_i64(
+    lf: LazyFrame,
+    column_name: &str,
+) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    lf.select([col(column_name).cast(DataType::Int64)])
+        .collect()?
+        .column(column_name)?
+        .i64()?
+        .into_iter()
+        .map(|x| Ok(x.ok_or(format!("{} not found", column_name))?))
+        .collect::<Result<Vec<i64>, Box<dyn std::error::Error>>>()
+}
+
+//
+
+/// Applies the maturity rules to the stored klines of `exchange`.
+pub fn pairs_mature(
+    exchange: &str,
+    interval: &str,
+    config: &crate::structs::MatureConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ts_now = utc_ms()?;
+    let ts_volume = ts_now - ti_ms(&config.volume_window)?;
+    let file_pairs = paths::dir_config().join(format!("pairs-{exchange}.json"));
+    let pairs_current: HashMap<String, serde_json::Value> = if file_pairs.is_file() {
+        json_read(&file_pairs)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut maturities = Vec::new();
+    for dir_entry in crate::paths::dir_list(&crate::paths::dir_klines_interval(exchange, interval))?
+    {
+        if dir_entry.extension != "feather" {
+            continue;
+        }
+        let df = feather_read(&dir_entry.path)?
+            .select([
+                col("ts")
+                    .filter(col("open").is_not_null())
+                    .min()
+                    .alias("ts_first"),
+                col("open")
+                    .null_count()
+                    .cast(DataType::Float64)
+                    .alias("n_missing"),
+                col("ts").count().cast(DataType::Float64).alias("n"),
+                col("quote asset volume")
+                    .cast(DataType::Float64)
+                    .filter(col("ts").gt_eq(lit(ts_volume)))
+                    .sum()
+                    .alias("volume"),
+            ])
+            .collect()?;
+        let value_f64 = |name: &str| -> Result<f64, Box<dyn std::error::Error>> {
+            Ok(df.column(name)?.f64()?.get(0).unwrap_or_default())
+        };
+        let ts_first = df.column("ts_first")?.i64()?.get(0);
+        let volume = value_f64("volume")?;
+        let gap_ratio = match value_f64("n")? {
+            x if x > 0.0 => value_f64("n_missing")? / x,
+            _ => 1.0,
+        };
+        let status = pairs_current
+            .get(&dir_entry.stem)
+            .and_then(|x| x.get("status"))
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string());
+
+        let mut reasons = Vec::new();
+        match ts_first {
+            None => reasons.push(String::from("no klines")),
+            Some(ts) if ts_now - ts < ti_ms(&config.history_min)? => reasons.push(format!(
+                "history since {} is shorter than {}",
+                unix_ms_to_string(ts)?,
+                config.history_min
+            )),
+            _ => {}
+        }
+        if volume < config.volume_min {
+            reasons.push(format!(
+                "quote volume {:.2} over {} is below {}",
+                volume, config.volume_window, config.volume_min
+            ));
+        }
+        if gap_ratio > config.gap_ratio_max {
+            reasons.push(format!(
+                "gap ratio {:.4} is above {}",
+                gap_ratio, config.gap_ratio_max
+            ));
+        }
+        if config.trading_only & (status.as_deref() != Some("TRADING")) {
+            reasons.push(match &status {
+                Some(x) => format!("status is {}", x),
+                None => String::from("not in current pairs"),
+            });
         }
+        if !reasons.is_empty() {
+            debug!("{} is not mature: {}", dir_entry.stem, reasons.join(", "));
+        }
+
+        maturities.push(crate::structs::PairMaturity {
+            pair: dir_entry.stem,
+            mature: reasons.is_empty(),
+            ts_first,
+            volume,
+            gap_ratio,
+            status,
+            reasons,
+        });
     }
+    maturities.sort_by(|a, b| a.pair.cmp(&b.pair));
 
-    let ts = utc_s()? - 3600 * 24 * 1;
-    let result = DataFrame::new(Vec::from([
-        Series::new("pair", pairs),
-        Series::new("ts", tss),
-    ]))?
-    .lazy()
-    .filter(col("ts").lt(lit(ts)))
-    .select([col("pair").unique()])
-    .collect()?
-    .column("pair")?
-    .utf8()?
-    .into_iter()
-    .map(|x| Ok(x.ok_or("item not found")?.to_string()))
-    .collect::<Result<Vec<String>, Box<dyn std::error::Error>>>()?;
+    let result = maturities
+        .iter()
+        .filter(|x| x.mature)
+        .map(|x| x.pair.clone())
+        .collect::<Vec<String>>();
+    info!("{} of {} pairs are mature", result.len(), maturities.len());
 
     json_write(
         &result,
         &paths::dir_config().join(format!("{}-pairs-mature.json", exchange)),
     )?;
+    json_write(
+        &maturities,
+        &paths::dir_config().join(format!("{}-pairs-maturity.json", exchange)),
+    )?;
 
     Ok(())
 }
 
 //
 
+/// Ranks `pairs` by quote volume and keeps the ones passing the ranking rules.
+///
+/// The 24 hour `tickers` decide, medians of the stored klines over the ranking window
+/// scaled to 24 hours stand in for pairs without a ticker.
+pub fn pairs_rank(
+    exchange: &str,
+    interval: &str,
+    config: &crate::structs::RankingConfig,
+    pairs: &[String],
+    tickers: &HashMap<String, crate::structs::Ticker>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let ts_window = utc_ms()? - ti_ms(&config.window)?;
+    let dir_path = crate::paths::dir_klines_interval(exchange, interval);
+    // months have no fixed length, their average one scales them
+    let ms_interval = match interval_months(interval)? {
+        Some(x) => x as f64 * 2_629_746_000.0,
+        None => ti_ms(interval)? as f64,
+    };
+    let days_interval = ms_interval / 86_400_000.0;
+
+    let mut rows = Vec::new();
+    for pair in pairs {
+        let file_path = dir_path.join(format!("{pair}.feather"));
+        let (volume_median, trades_median) = if file_path.is_file() {
+            let df = feather_read(&file_path)?
+                .filter(col("ts").gt_eq(lit(ts_window)))
+                .select([
+                    col("quote asset volume").cast(DataType::Float64).median(),
+                    col("number of trades").cast(DataType::Float64).median(),
+                ])
+                .collect()?;
+            (
+                df.column("quote asset volume")?.f64()?.get(0),
+                df.column("number of trades")?.f64()?.get(0),
+            )
+        } else {
+            (None, None)
+        };
+        let ticker = tickers.get(pair);
+        let volume = ticker
+            .map(|x| x.quote_volume)
+            .or(volume_median.map(|x| x / days_interval))
+            .unwrap_or_default();
+        rows.push((pair.clone(), volume, ticker, volume_median, trades_median));
+    }
+    rows.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut selected = Vec::new();
+    for (pair, volume, ..) in rows.iter() {
+        if config.top_n_by_volume.is_some_and(|x| selected.len() >= x) {
+            break;
+        }
+        if *volume >= config.min_quote_volume {
+            selected.push(pair.clone());
+        }
+    }
+    info!(
+        "{} of {} pairs selected by volume",
+        selected.len(),
+        rows.len()
+    );
+
+    let mut df = DataFrame::new(Vec::from([
+        Series::new("rank", (1..=rows.len() as u32).collect::<Vec<u32>>()),
+        Series::new(
+            "pair",
+            rows.iter().map(|x| x.0.clone()).collect::<Vec<String>>(),
+        ),
+        Series::new(
+            "quote volume 24h",
+            rows.iter()
+                .map(|x| x.2.map(|t| t.quote_volume))
+                .collect::<Vec<Option<f64>>>(),
+        ),
+        Series::new(
+            "number of trades 24h",
+            rows.iter()
+                .map(|x| x.2.map(|t| t.trades))
+                .collect::<Vec<Option<u64>>>(),
+        ),
+        Series::new(
+            "quote asset volume median",
+            rows.iter().map(|x| x.3).collect::<Vec<Option<f64>>>(),
+        ),
+        Series::new(
+            "number of trades median",
+            rows.iter().map(|x| x.4).collect::<Vec<Option<f64>>>(),
+        ),
+        Series::new(
+            "selected",
+            rows.iter()
+                .map(|x| selected.contains(&x.0))
+                .collect::<Vec<bool>>(),
+        ),
+    ]))?;
+    csv_write(
+        &mut df,
+        &paths::dir_config().join(format!("{}-pairs-ranked.csv", exchange)),
+        Some(2),
+    )?;
+
+    Ok(pairs
+        .iter()
+        .filter(|x| selected.contains(x))
+        .cloned()
+        .collect())
+}
+
+//
+
 pub fn timestamps_missing(
     lf: LazyFrame,
     interval_base: &str,
 ) -> Result<LazyFrame, Box<dyn std::error::Error>> {
-    let ts_max = column_maxu(lf.clone(), "ts")? as u32;
-    let ts_min = column_minu(lf.clone(), "ts")? as u32;
-    let ts = DataFrame::new(Vec::from([Series::new(
-        "ts",
+    let ts_max = column_maxi(lf.clone(), "ts")?;
+    let ts_min = column_mini(lf.clone(), "ts")?;
+    let tss = if interval_months(interval_base)?.is_some() {
+        let mut tss = Vec::new();
+        let mut ts = ts_min;
+        while ts <= ts_max {
+            tss.push(ts);
+            ts = interval_add_ms(ts_min, interval_base, tss.len() as u32)?;
+        }
+        tss
+    } else {
         (ts_min..ts_max + 1)
-            .step_by(ti_s(interval_base)? as usize)
-            .collect::<Vec<u32>>(),
-    )]))?
-    .lazy();
+            .step_by(ti_ms(interval_base)? as usize)
+            .collect::<Vec<i64>>()
+    };
+    let ts = DataFrame::new(Vec::from([Series::new("ts", tss)]))?.lazy();
     Ok(ts.join(lf, [col("ts")], [col("ts")], JoinType::Left))
 }
+
+//
+
+pub fn timestamps_gaps(
+    lf: LazyFrame,
+    interval_base: &str,
+) -> Result<Vec<(i64, i64)>, Box<dyn std::error::Error>> {
+    let tss = column_values_i64(
+        timestamps_missing(lf, interval_base)?
+            .filter(col("open").is_null())
+            .sort("ts", Default::default()),
+        "ts",
+    )?;
+
+    let mut gaps: Vec<(i64, i64)> = Vec::new();
+    for ts in tss {
+        match gaps.last_mut() {
+            Some(gap) if interval_add_ms(gap.1, interval_base, 1)? == ts => gap.1 = ts,
+            _ => gaps.push((ts, ts)),
+        }
+    }
+
+    Ok(gaps)
+}
+
+//
+
+fn klines_resample_every(
+    interval_base: &str,
+    interval: &str,
+) -> Result<Duration, Box<dyn std::error::Error>> {
+    let (length, period) = interval_parse(interval)?;
+    let valid = match (interval_months(interval_base)?, interval_months(interval)?) {
+        (None, None) => {
+            let (seconds_base, seconds) = (ti_s(interval_base)?, ti_s(interval)?);
+            (seconds > seconds_base) & (seconds % seconds_base == 0)
+        }
+        (None, Some(_)) => 86_400 % ti_s(interval_base)? == 0,
+        (Some(months_base), Some(months)) => (months > months_base) & (months % months_base == 0),
+        (Some(_), None) => false,
+    };
+    if !valid {
+        return Err(format!("{} can not be resampled to {}", interval_base, interval).into());
+    }
+
+    Ok(match period {
+        'M' => Duration::parse(&format!("{}mo", length)),
+        'y' => Duration::parse(&format!("{}y", length)),
+        _ => Duration::parse(interval),
+    })
+}
+
+//
+
+pub fn klines_resample(
+    lf: LazyFrame,
+    interval_base: &str,
+    interval: &str,
+) -> Result<LazyFrame, Box<dyn std::error::Error>> {
+    let every = klines_resample_every(interval_base, interval)?;
+    Ok(lf
+        .filter(col("open").is_not_null())
+        .with_column(
+            col("ts")
+                .cast(DataType::Datetime(TimeUnit::Milliseconds, None))
+                .alias("datetime"),
+        )
+        .sort("datetime", Default::default())
+        .groupby_dynamic(
+            col("datetime"),
+            [],
+            DynamicGroupOptions {
+                every,
+                period: every,
+                offset: Duration::parse("0s"),
+                truncate: true,
+                closed_window: ClosedWindow::Left,
+                ..Default::default()
+            },
+        )
+        .agg([
+            col("open").first(),
+            col("high").max(),
+            col("low").min(),
+            col("close").last(),
+            col("quote asset volume").sum(),
+            col("number of trades").sum().cast(DataType::UInt32),
+        ])
+        .select([
+            col("datetime").cast(DataType::Int64).alias("ts"),
+            col("open"),
+            col("high"),
+            col("low"),
+            col("close"),
+            col("quote asset volume"),
+            col("number of trades"),
+        ]))
+}
+
+//
+
+pub fn klines_resample_write(
+    exchange: &str,
+    interval_base: &str,
+    interval: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir_path = crate::paths::dir_klines_interval(exchange, interval);
+    for dir_entry in
+        crate::paths::dir_list(&crate::paths::dir_klines_interval(exchange, interval_base))?
+    {
+        if dir_entry.extension == "feather" {
+            let mut df = klines_resample(feather_read(&dir_entry.path)?, interval_base, interval)?
+                .collect()?;
+            feather_write(
+                &mut df,
+                &dir_path.join(format!("{}.feather", dir_entry.stem)),
+            )?;
+            info!(
+                "resampled {} to {}: {} x {}",
+                dir_entry.stem,
+                interval,
+                &df.shape().0,
+                &df.shape().1
+            );
+        }
+    }
+
+    Ok(())
+}
+
+//
+
+fn feather_files(dir_path: &PathBuf) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut file_paths = Vec::new();
+    for dir_entry in crate::paths::dir_list(dir_path)? {
+        if dir_entry.path.is_dir() {
+            file_paths.extend(feather_files(&dir_entry.path)?);
+        } else if dir_entry.extension == "feather" {
+            file_paths.push(dir_entry.path);
+        }
+    }
+
+    Ok(file_paths)
+}
+
+//
+
+pub fn feather_migrate() -> Result<(), Box<dyn std::error::Error>> {
+    // klines ts used to be u32 seconds, trades and withdrawals times u64 milliseconds
+    let columns_time = ["ts", "time", "recorded_at", "applytime", "completetime"];
+    let mut file_paths = feather_files(&crate::paths::dir_klines())?;
+    file_paths.extend(feather_files(&crate::paths::dir_trades())?);
+    file_paths.extend(feather_files(&crate::paths::dir_withdrawals())?);
+
+    for file_path in file_paths {
+        let lf = feather_read(&file_path)?;
+        let columns = lf
+            .schema()?
+            .iter()
+            .filter(|(name, dtype)| {
+                columns_time.contains(&name.as_str())
+                    & [DataType::UInt32, DataType::UInt64].contains(dtype)
+            })
+            .map(|(name, dtype)| (name.to_string(), dtype.clone()))
+            .collect::<Vec<(String, DataType)>>();
+        if columns.is_empty() {
+            continue;
+        }
+        info!("migrating {}", file_path.as_path().display());
+
+        let mut df = lf
+            .with_columns(
+                columns
+                    .iter()
+                    .map(|(name, dtype)| {
+                        if *dtype == DataType::UInt32 {
+                            col(name).cast(DataType::Int64) * lit(1000i64)
+                        } else {
+                            col(name).cast(DataType::Int64)
+                        }
+                    })
+                    .collect::<Vec<Expr>>(),
+            )
+            .collect()?;
+        feather_write(&mut df, &file_path)?;
+
+        // gaps of klines stored as klines/{exchange}/{interval}/{pair}.feather
+        let file_path_gaps = file_path
+            .strip_prefix(crate::paths::dir_klines())
+            .map(|x| crate::paths::dir_gaps().join(x).with_extension("json"));
+        if let Ok(file_path_gaps) = file_path_gaps {
+            if file_path_gaps.is_file() {
+                let mut gaps: Vec<crate::structs::KlinesGap> = json_read(&file_path_gaps)?;
+                for gap in gaps.iter_mut() {
+                    gap.ts_start *= 1000;
+                    gap.ts_end *= 1000;
+                }
+                json_write(&gaps, &file_path_gaps)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//
+
+/// Moves data stored before named accounts, `data/{trades,withdrawals}/{exchange}/*.feather`, to
+/// the first account of the exchange and adds its `account` column.
+pub fn accounts_migrate(
+    config: &crate::structs::AppConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for exchange in ["binance", "kraken"] {
+        if !config.api_clients.contains_key(exchange) {
+            continue;
+        }
+        let account = crate::api::accounts_get(exchange, config)?
+            .into_iter()
+            .next()
+            .ok_or("account not found")?
+            .name;
+
+        for dir_kind in [crate::paths::dir_trades(), crate::paths::dir_withdrawals()] {
+            let dir_path = dir_kind.join(exchange);
+            if !dir_path.is_dir() {
+                continue;
+            }
+            for dir_entry in crate::paths::dir_list(&dir_path)? {
+                if !dir_entry.path.is_file() | (dir_entry.extension != "feather") {
+                    continue;
+                }
+                info!("migrating {}", dir_entry.path.as_path().display());
+                let file_path = crate::paths::dir_account(&dir_kind, exchange, &account)
+                    .join(dir_entry.path.file_name().ok_or("file name not found")?);
+                let lf = feather_read(&dir_entry.path)?
+                    .with_column(lit(account.as_str()).alias("account"));
+                let mut df = if file_path.exists() {
+                    migrate_merge(lf, &file_path)?
+                } else {
+                    lf.collect()?
+                };
+                feather_write(&mut df, &file_path)?;
+                std::fs::remove_file(&dir_entry.path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//
+
+// the account file at `file_path` with the records of `lf` it misses, an error if it lacks
+// one of their columns
+fn migrate_merge(
+    lf: LazyFrame,
+    file_path: &PathBuf,
+) -> Result<DataFrame, Box<dyn std::error::Error>> {
+    info!("merging into existing {}", file_path.as_path().display());
+    let schema = lf.schema()?;
+    let schema_stored = feather_read(file_path)?.schema()?;
+    if let Some(name) = schema.iter_names().find(|x| schema_stored.get(x).is_none()) {
+        return Err(format!(
+            "column {} not found in {}, move the file by hand",
+            name,
+            file_path.as_path().display()
+        )
+        .into());
+    }
+    // stored and migrated copies of a record differ at most in these
+    let subset = schema
+        .iter_names()
+        .filter(|x| !["recorded_at", "account"].contains(&x.as_str()))
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>();
+    let lf = lf.select(
+        schema_stored
+            .iter()
+            .map(|(name, dtype)| match schema.get(name) {
+                Some(_) => col(name).cast(dtype.clone()),
+                None => lit(Null {}).cast(dtype.clone()).alias(name),
+            })
+            .collect::<Vec<Expr>>(),
+    );
+
+    Ok(concat([feather_read(file_path)?, lf], true, true)?
+        .unique_stable(Some(subset), UniqueKeepStrategy::First)
+        .collect()?)
+}
@@ -1,25 +1,91 @@
+use log::warn;
+
 fn main() {
     env_logger::init();
 
-    let config = api::yaml_read(&api::paths::file_config()).expect("config failed");
+    let config: api::structs::AppConfig =
+        api::yaml_read(&api::paths::file_config()).expect("config failed");
+    let args = std::env::args().collect::<Vec<String>>();
+
+    api::feather_migrate().expect("migrate failed");
+    api::accounts_migrate(&config).expect("migrate failed");
+
+    match args.get(1).map(|x| x.as_str()) {
+        None | Some("sync") => sync(&config),
+        Some("resample") => {
+            let interval = args.get(2).expect("interval not found");
+            let exchange = args.get(3).map(|x| x.as_str()).unwrap_or("binance");
+            api::klines_resample_write(exchange, &config.history.interval, interval)
+                .expect("resample failed");
+        }
+        Some("changes") => changes(&config, &args[2..]),
+        Some(x) => panic!("command {} not found", x),
+    }
+}
+
+//
+
+fn arg_get<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|x| args.get(x + 1))
+        .map(|x| x.as_str())
+}
 
+//
+
+fn changes(config: &api::structs::AppConfig, args: &[String]) {
+    let timezone = api::timezone_parse(&config.timezone).expect("timezone failed");
+    let ts_get = |name: &str| {
+        arg_get(args, name).map(|x| api::date_to_unix_ms_tz(x, timezone).expect("date failed"))
+    };
+    let filter = api::changes::ChangesFilter {
+        kinds: arg_get(args, "--kind")
+            .map(|x| x.split(',').map(|x| x.to_string()).collect())
+            .unwrap_or_default(),
+        ts_from: ts_get("--from"),
+        // a date without time includes the whole day
+        ts_to: ts_get("--to").map(|x| match arg_get(args, "--to") {
+            Some(date) if date.len() == 10 => {
+                api::day_bounds_ms(x, timezone).expect("day failed").1 - 1
+            }
+            _ => x,
+        }),
+        symbol: arg_get(args, "--symbol").map(|x| x.to_string()),
+    };
+
+    let changes =
+        api::changes::changes_get(&filter, &config.diff.identity_keys).expect("changes failed");
+    let output = match arg_get(args, "--format").unwrap_or("markdown") {
+        "markdown" => api::changes::changes_markdown(&changes, timezone),
+        "json" => api::changes::changes_json(&changes),
+        x => panic!("format {} not found", x),
+    }
+    .expect("output failed");
+    println!("{}", output);
+}
+
+//
+
+fn sync(config: &api::structs::AppConfig) {
     // coinmarketcap
     {
-        let mut api = api::api::coinmarketcap::API::new(&config).expect("api failed");
+        let mut api = api::api::coinmarketcap::API::new(config).expect("api failed");
         api.fiat_get().expect("fiat failed");
         api.stablecoins_get().expect("stablecoins failed");
         println!("throttler: {:?}", api.client.throttler.len());
     }
 
-    // binance
+    // binance, klines are public and downloaded with the first account only
+    for (index, mut api) in api::api::binance::API::new_accounts(config)
+        .expect("api failed")
+        .into_iter()
+        .enumerate()
     {
-        let mut api = api::api::binance::API::new(&config).expect("api failed");
-        if config.history.do_history {
+        if config.history.do_history & (index == 0) {
             api.history_get().expect("history failed");
         }
-        if config.trades.do_trades.contains(&api.label.to_string()) {
-            api.trades_get().expect("trades failed");
-        }
+        // trades look up withdrawn assets, so withdrawals go first
         if config
             .withdrawals
             .do_withdrawals
@@ -27,14 +93,48 @@ fn main() {
         {
             api.withdrawals_get().expect("withdrawals failed");
         }
+        if config.trades.do_trades.contains(&api.label.to_string()) {
+            api.trades_get().expect("trades failed");
+        }
+        if config
+            .conversions
+            .do_conversions
+            .contains(&api.label.to_string())
+        {
+            api.conversions_get().expect("conversions failed");
+        }
+        if config.income.do_income.contains(&api.label.to_string()) {
+            api.income_get().expect("income failed");
+        }
+        if config
+            .transfers
+            .do_transfers
+            .contains(&api.label.to_string())
+        {
+            api.transfers_get().expect("transfers failed");
+        }
+        // a key without the permission skips them
+        if config.margin.do_margin.contains(&api.label.to_string()) {
+            match api.margin_get() {
+                Err(e) if api::api::unauthorized(e.as_ref()) => {
+                    warn!("{} margin skipped, no permission", api.account.name)
+                }
+                result => result.expect("margin failed"),
+            }
+        }
+        if config.futures.do_futures.contains(&api.label.to_string()) {
+            match api.futures_get() {
+                Err(e) if api::api::unauthorized(e.as_ref()) => {
+                    warn!("{} futures skipped, no permission", api.account.name)
+                }
+                result => result.expect("futures failed"),
+            }
+        }
         println!("throttler: {:?}", api.client.throttler.len());
     }
 
     // kraken
-    {
-        let mut api = api::api::kraken::API::new(&config).expect("api failed");
-        api.pairs_get().expect("pairs failed");
-
+    for mut api in api::api::kraken::API::new_accounts(config).expect("api failed") {
         if config.trades.do_trades.contains(&api.label.to_string()) {
             api.trades_get().expect("trades failed");
         }
@@ -0,0 +1,230 @@
+use log::{info, warn};
+use std::io::Write;
+
+//
+
+use crate::changes::Change;
+use crate::structs::{AppConfig, NotificationRule, NotifierConfig};
+
+//
+//
+//
+
+#[derive(Debug, serde::Serialize)]
+pub struct Notification<'a> {
+    pub kind: &'a str,
+    pub timestamp: String,
+    pub changes: Vec<Change>,
+    pub summary: String,
+}
+
+//
+
+// created by the first notification, runtimes cannot be dropped from async code
+static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+
+/// Sends the changes of `diff` matching the configured rules to all notifiers.
+///
+/// Blocking wrapper of [`notify_async`], can be called from async code too.
+pub fn notify(
+    config: &AppConfig,
+    kind: &str,
+    ts: i64,
+    diff: &crate::diff::Diff,
+    data_new: &serde_json::Value,
+    data_old: &serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if config.notifications.notifiers.is_empty() {
+        return Ok(());
+    }
+    if RUNTIME.get().is_none() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let _ = RUNTIME.set(runtime);
+    }
+
+    crate::api::block_on(
+        RUNTIME.get().ok_or("runtime not found")?,
+        notify_async(config, kind, ts, diff, data_new, data_old),
+    )
+    .map_err(|e| e.to_string().into())
+}
+
+//
+
+pub async fn notify_async(
+    config: &AppConfig,
+    kind: &str,
+    ts: i64,
+    diff: &crate::diff::Diff,
+    data_new: &serde_json::Value,
+    data_old: &serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if config.notifications.notifiers.is_empty() {
+        return Ok(());
+    }
+
+    let changes =
+        crate::changes::diff_changes_get(diff, ts, kind, data_new, &config.diff.identity_keys)
+            .into_iter()
+            .filter(|x| {
+                config.notifications.rules.is_empty()
+                    | config
+                        .notifications
+                        .rules
+                        .iter()
+                        .any(|rule| rule_matches(rule, x, data_new, data_old))
+            })
+            .collect::<Vec<Change>>();
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let timezone = crate::timezone_parse(&config.timezone).map_err(|e| e.to_string())?;
+    let notification = Notification {
+        kind,
+        timestamp: crate::unix_ms_to_string_tz(ts, timezone).map_err(|e| e.to_string())?,
+        summary: crate::changes::changes_markdown(&changes, timezone).map_err(|e| e.to_string())?,
+        changes,
+    };
+    info!(
+        "{} changes of {} to notify",
+        notification.changes.len(),
+        kind
+    );
+
+    // a failing notifier does not stop the download
+    for notifier in config.notifications.notifiers.iter() {
+        if let Err(e) = notifier_send(config, notifier, &notification).await {
+            warn!("notifier {:?} failed: {}", notifier, e);
+        }
+    }
+
+    Ok(())
+}
+
+//
+
+fn rule_matches(
+    rule: &NotificationRule,
+    change: &Change,
+    data_new: &serde_json::Value,
+    data_old: &serde_json::Value,
+) -> bool {
+    // delisted entries are only found in the old data
+    let entry = data_new
+        .get(&change.symbol)
+        .or_else(|| data_old.get(&change.symbol));
+
+    (rule.kinds.is_empty() | rule.kinds.contains(&change.kind))
+        & (rule.changes.is_empty() | rule.changes.contains(&change.change))
+        & rule
+            .fields
+            .iter()
+            .all(|(k, v)| entry.and_then(|x| x.get(k)) == Some(v))
+}
+
+//
+
+async fn notifier_send(
+    config: &AppConfig,
+    notifier: &NotifierConfig,
+    notification: &Notification<'_>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match notifier {
+        NotifierConfig::Webhook { url } => {
+            reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(
+                    crate::ti_s(&config.api_timeout).map_err(|e| e.to_string())? as u64,
+                ))
+                .build()?
+                .post(url)
+                .json(notification)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        // mail servers and commands are waited for off the runtime threads
+        NotifierConfig::Email { .. } | NotifierConfig::Command { .. } => {
+            let notifier = notifier.clone();
+            let subject = format!(
+                "ant: {} changes of {}",
+                notification.changes.len(),
+                notification.kind
+            );
+            let summary = notification.summary.clone();
+            let content = serde_json::to_string(notification)?;
+            tokio::task::spawn_blocking(move || {
+                notifier_send_blocking(&notifier, &subject, &summary, &content)
+                    .map_err(|e| e.to_string())
+            })
+            .await??;
+        }
+    }
+
+    Ok(())
+}
+
+//
+
+fn notifier_send_blocking(
+    notifier: &NotifierConfig,
+    subject: &str,
+    summary: &str,
+    content: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match notifier {
+        NotifierConfig::Email {
+            host,
+            port,
+            starttls,
+            from,
+            to,
+            username_env,
+            password_env,
+        } => {
+            let mut message = lettre::Message::builder()
+                .from(from.parse()?)
+                .subject(subject);
+            for x in to {
+                message = message.to(x.parse()?);
+            }
+            let message = message.body(summary.to_string())?;
+
+            let mut transport = if *starttls {
+                lettre::SmtpTransport::starttls_relay(host)?
+            } else {
+                lettre::SmtpTransport::builder_dangerous(host)
+            }
+            .port(*port);
+            if let (Some(username), Some(password)) = (username_env, password_env) {
+                transport = transport.credentials(
+                    lettre::transport::smtp::authentication::Credentials::new(
+                        std::env::var(username)?,
+                        std::env::var(password)?,
+                    ),
+                );
+            }
+            lettre::Transport::send(&transport.build(), &message)?;
+        }
+        NotifierConfig::Command { program, args } => {
+            let mut child = std::process::Command::new(program)
+                .args(args)
+                .stdin(std::process::Stdio::piped())
+                .spawn()?;
+            child
+                .stdin
+                .take()
+                .ok_or("stdin not found")?
+                .write_all(content.as_bytes())?;
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(format!("{} exited with {}", program, status).into());
+            }
+        }
+        NotifierConfig::Webhook { .. } => return Err("webhooks are sent async".into()),
+    }
+
+    Ok(())
+}
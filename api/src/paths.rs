@@ -19,15 +19,16 @@ pub fn dir_list(
                     path: path.clone(),
                     stem: path
                         .clone()
-                        .extension()
+                        .file_stem()
                         .ok_or("stem not found")?
                         .to_os_string()
                         .into_string()
                         .or(Err("string failed"))?,
+                    // directories have no extension
                     extension: path
                         .clone()
-                        .file_stem()
-                        .ok_or("extension not found")?
+                        .extension()
+                        .unwrap_or_default()
                         .to_os_string()
                         .into_string()
                         .or(Err("string failed"))?,
@@ -57,6 +58,24 @@ pub fn dir_klines() -> PathBuf {
     dir_path
 }
 
+pub fn dir_gaps() -> PathBuf {
+    let dir_path = dir_data().join("gaps");
+    dir_create(&dir_path);
+    dir_path
+}
+
+pub fn dir_klines_interval(exchange: &str, interval: &str) -> PathBuf {
+    let dir_path = dir_klines().join(exchange).join(interval);
+    dir_create(&dir_path);
+    dir_path
+}
+
+pub fn dir_snapshots() -> PathBuf {
+    let dir_path = dir_data().join("snapshots");
+    dir_create(&dir_path);
+    dir_path
+}
+
 pub fn dir_trades() -> PathBuf {
     let dir_path = dir_data().join("trades");
     dir_create(&dir_path);
@@ -69,6 +88,31 @@ pub fn dir_withdrawals() -> PathBuf {
     dir_path
 }
 
+pub fn dir_income() -> PathBuf {
+    let dir_path = dir_data().join("income");
+    dir_create(&dir_path);
+    dir_path
+}
+
+pub fn dir_transfers() -> PathBuf {
+    let dir_path = dir_data().join("transfers");
+    dir_create(&dir_path);
+    dir_path
+}
+
+// data of one account of an exchange, e.g. data/trades/binance/main
+pub fn dir_account(dir_path: &std::path::Path, exchange: &str, account: &str) -> PathBuf {
+    let dir_path = dir_path.join(exchange).join(account);
+    dir_create(&dir_path);
+    dir_path
+}
+
+pub fn dir_cassettes() -> PathBuf {
+    let dir_path = dir_root().join("cassettes");
+    dir_create(&dir_path);
+    dir_path
+}
+
 pub fn dir_logs() -> PathBuf {
     let dir_path = dir_root().join("logs");
     dir_create(&dir_path);
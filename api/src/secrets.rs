@@ -0,0 +1,90 @@
+use log::debug;
+use zeroize::Zeroizing;
+
+//
+
+use crate::structs::{Secret, SecretSource};
+
+//
+//
+//
+
+/// Reads a key or secret from its source, errors name the source but never hold the secret.
+pub fn secret_get(source: &SecretSource) -> Result<Secret, Box<dyn std::error::Error>> {
+    let text = match source {
+        SecretSource::Env { name } => {
+            debug!("secret from env {}", name);
+            return Ok(Secret(Zeroizing::new(
+                std::env::var(name).map_err(|_| format!("env {} not found", name))?,
+            )));
+        }
+        SecretSource::File { path } => {
+            debug!("secret from file {}", path);
+            file_check(path)?;
+            Zeroizing::new(std::fs::read(path)?)
+        }
+        SecretSource::Age { path, identity } => {
+            file_check(identity)?;
+            command_output("age", &["--decrypt", "--identity", identity, path])?
+        }
+        SecretSource::Gpg { path } => {
+            command_output("gpg", &["--quiet", "--batch", "--decrypt", path])?
+        }
+        SecretSource::Command { program, args } => command_output(
+            program,
+            &args.iter().map(|x| x.as_str()).collect::<Vec<&str>>(),
+        )?,
+    };
+
+    let line = std::str::from_utf8(&text)
+        .map_err(|_| "secret is not utf-8")?
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if line.is_empty() {
+        return Err("secret is empty".into());
+    }
+
+    Ok(Secret(Zeroizing::new(line.to_string())))
+}
+
+//
+
+// files holding secrets must not be readable by the group or others
+fn file_check(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = std::fs::metadata(path).map_err(|_| format!("file {} not found", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(format!("file {} has mode {:o}, expected 600", path, mode & 0o777).into());
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+
+    Ok(())
+}
+
+//
+
+fn command_output(
+    program: &str,
+    args: &[&str],
+) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    debug!("secret from command {}", program);
+    let output = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| format!("{} failed: {}", program, e))?;
+    let stdout = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        return Err(format!("{} exited with {}", program, output.status).into());
+    }
+
+    Ok(stdout)
+}
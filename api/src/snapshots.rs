@@ -0,0 +1,159 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::{debug, info};
+use sha2::Digest;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+//
+
+use crate::structs::{SnapshotEntry, SymbolListings};
+
+//
+//
+//
+
+fn file_index(kind: &str) -> PathBuf {
+    crate::paths::dir_snapshots().join(kind).join("index.json")
+}
+
+//
+
+fn file_listings(kind: &str) -> PathBuf {
+    crate::paths::dir_snapshots()
+        .join(kind)
+        .join("symbols.json")
+}
+
+//
+
+fn file_object(kind: &str, hash: &str) -> PathBuf {
+    let dir_path = crate::paths::dir_snapshots().join(kind).join("objects");
+    crate::paths::dir_create(&dir_path);
+    dir_path.join(format!("{hash}.json.gz"))
+}
+
+//
+
+pub fn index_read(kind: &str) -> Result<Vec<SnapshotEntry>, Box<dyn std::error::Error>> {
+    let file_path = file_index(kind);
+    if file_path.is_file() {
+        crate::json_read(&file_path)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+//
+
+/// Stores `data` as the reference data of `kind` valid from `ts` on.
+pub fn snapshot_write(
+    kind: &str,
+    ts: i64,
+    data: &serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // objects are sorted by key, the same content always gets the same hash
+    let content = serde_json::to_string(data)?;
+    let hash = hex::encode(sha2::Sha256::digest(content.as_bytes()));
+
+    let file_path = file_object(kind, &hash);
+    if !file_path.is_file() {
+        debug!("snapshot write - {}", file_path.as_path().display());
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes())?;
+        std::fs::write(&file_path, encoder.finish()?)?;
+    }
+
+    let mut index = index_read(kind)?;
+    if index.last().is_some_and(|x| x.hash == hash) {
+        return Ok(());
+    }
+    if index.last().is_some_and(|x| x.ts > ts) {
+        return Err(format!("{} snapshot at {} is older than the last one", kind, ts).into());
+    }
+    info!(
+        "{} snapshot {} from {}",
+        kind,
+        hash,
+        crate::unix_ms_to_string(ts)?
+    );
+    index.push(SnapshotEntry { ts, hash });
+    crate::json_write(&index, &file_index(kind))?;
+    listings_read(kind)?;
+
+    Ok(())
+}
+
+//
+
+/// Listings of every symbol of `kind`, snapshots not applied yet are read once and stored.
+pub fn listings_read(kind: &str) -> Result<SymbolListings, Box<dyn std::error::Error>> {
+    let file_path = file_listings(kind);
+    let mut listings: SymbolListings = if file_path.is_file() {
+        crate::json_read(&file_path)?
+    } else {
+        SymbolListings::default()
+    };
+    let index = index_read(kind)?;
+    if listings.n_entries == index.len() {
+        return Ok(listings);
+    }
+
+    for entry in index.iter().skip(listings.n_entries) {
+        let data = snapshot_read(kind, entry)?;
+        let data = data.as_object().ok_or("object not found")?;
+        for (symbol, listing) in listings.symbols.iter_mut() {
+            if !data.contains_key(symbol) {
+                listing.listed = false;
+            }
+        }
+        for (symbol, value) in data {
+            let listing = listings.symbols.entry(symbol.clone()).or_default();
+            listing.listed = true;
+            listing.last = value.clone();
+        }
+        // periods close at the first snapshot a symbol is missing or not trading in
+        for (symbol, listing) in listings.symbols.iter_mut() {
+            let trading = data
+                .get(symbol)
+                .and_then(|x| x.get("status"))
+                .and_then(|x| x.as_str())
+                == Some("TRADING");
+            match (listing.periods.last_mut(), trading) {
+                (Some((_, ts_end @ None)), false) => *ts_end = Some(entry.ts),
+                (Some((_, None)), true) => {}
+                (_, true) => listing.periods.push((entry.ts, None)),
+                (_, false) => {}
+            }
+        }
+    }
+    listings.n_entries = index.len();
+    crate::json_write(&listings, &file_path)?;
+
+    Ok(listings)
+}
+
+//
+
+pub fn snapshot_read(
+    kind: &str,
+    entry: &SnapshotEntry,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let file_path = file_object(kind, &entry.hash);
+    debug!("snapshot read - {}", file_path.as_path().display());
+    let mut content = String::new();
+    GzDecoder::new(std::fs::File::open(&file_path)?).read_to_string(&mut content)?;
+
+    Ok(serde_json::from_str(&content)?)
+}
+
+//
+
+/// The reference data of `kind` valid at `ts`, none before the first snapshot.
+pub fn as_of(kind: &str, ts: i64) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    let index = index_read(kind)?;
+    let Some(entry) = index.iter().rev().find(|x| x.ts <= ts) else {
+        return Ok(None);
+    };
+
+    Ok(Some(snapshot_read(kind, entry)?))
+}
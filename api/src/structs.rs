@@ -1,44 +1,136 @@
-pub struct APIClient<'a> {
-    pub client: reqwest::blocking::Client,
+/// Client of one exchange, clones share the request limit and can be used from async code.
+#[derive(Clone)]
+pub struct APIClient {
+    pub client: reqwest::Client,
     pub headers: reqwest::header::HeaderMap,
-    pub url: &'a str,
+    pub url: String,
     pub limit_requests: usize,
-    pub limit_period: &'a str,
-    pub limit_status_codes: &'a Vec<u16>,
+    pub limit_period: String,
+    pub limit_status_codes: Vec<u16>,
     pub api_retries: usize,
-    pub throttler: Vec<u64>,
+    pub throttler: Throttler,
+    pub weights: std::collections::HashMap<String, usize>,
+    pub concurrency: usize,
+    pub label: String,
+    pub account: String,
+    pub cassette_mode: CassetteMode,
+    pub cassette_index: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    // created by the first blocking request only
+    pub runtime: std::sync::Arc<std::sync::OnceLock<tokio::runtime::Runtime>>,
 }
 
-impl APIClient<'_> {
-    pub fn throttler_push(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.throttler.push(crate::utc_ms()?);
+impl APIClient {
+    /// Weight of a request to `url`, the weights of the client config by path or 1.
+    pub fn weight(&self, url: &str) -> usize {
+        let path = url.strip_prefix(&self.url).unwrap_or(url);
+        let path = path.split('?').next().unwrap_or_default();
+        self.weights.get(path).copied().unwrap_or(1)
+    }
+
+    /// Moves the slot taken at `ts` to the end of the request.
+    pub fn throttler_push(&self, ts: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut throttler = self.throttler.0.lock().map_err(|_| "throttler poisoned")?;
+        if let Some(x) = throttler.iter_mut().find(|x| x.0 == ts) {
+            x.0 = crate::utc_ms().map_err(|e| e.to_string())?;
+        }
+        throttler.sort();
         Ok(())
     }
-    pub fn throttler_sleep(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        while self.throttler.len() >= self.limit_requests {
-            std::thread::sleep(std::time::Duration::from_millis(
-                crate::utc_ms()? - self.throttler[0],
-            ));
-            let threshold = crate::utc_ms()? - crate::ti_ms(self.limit_period)?;
-            self.throttler.retain(|x| *x >= threshold)
+
+    /// Waits until `weight` fits into the limit and takes it, clients of one url share the slots.
+    ///
+    /// A request weighing more than the whole limit waits for an empty period.
+    pub async fn throttler_sleep(
+        &self,
+        weight: usize,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let period = crate::ti_ms(&self.limit_period).map_err(|e| e.to_string())?;
+        loop {
+            let wait = {
+                let mut throttler = self.throttler.0.lock().map_err(|_| "throttler poisoned")?;
+                let ts = crate::utc_ms().map_err(|e| e.to_string())?;
+                let threshold = ts - period;
+                throttler.retain(|x| x.0 > threshold);
+                let used = throttler.iter().map(|x| x.1).sum::<usize>();
+                if throttler.is_empty() | (used + weight <= self.limit_requests) {
+                    throttler.push((ts, weight));
+                    return Ok(ts);
+                }
+                throttler[0].0 - threshold
+            };
+            tokio::time::sleep(std::time::Duration::from_millis(wait.try_into()?)).await;
         }
+    }
+}
 
-        Ok(())
+//
+
+/// Request timestamps and weights of one url within the limit period.
+#[derive(Clone, Debug, Default)]
+pub struct Throttler(std::sync::Arc<std::sync::Mutex<Vec<(i64, usize)>>>);
+
+impl Throttler {
+    pub fn len(&self) -> usize {
+        self.0.lock().map(|x| x.len()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sum of the weights within the limit period.
+    pub fn weight(&self) -> usize {
+        self.0
+            .lock()
+            .map(|x| x.iter().map(|x| x.1).sum())
+            .unwrap_or_default()
     }
 }
 
 //
 
+/// Sections and fields missing from a config.yaml of an earlier version take defaults that
+/// keep its behaviour, i.e. the features added since are off.
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct AppConfig {
     pub api_retries: usize,
     pub api_timeout: String,
+    #[serde(default = "timezone_default")]
+    pub timezone: String,
     pub history: HistoryConfig,
     pub trades: TradesConfig,
     pub withdrawals: WithdrawalsConfig,
+    #[serde(default)]
+    pub conversions: ConversionsConfig,
+    #[serde(default)]
+    pub income: IncomeConfig,
+    #[serde(default)]
+    pub transfers: TransfersConfig,
+    #[serde(default)]
+    pub margin: MarginConfig,
+    #[serde(default)]
+    pub futures: FuturesConfig,
+    #[serde(default)]
+    pub diff: DiffConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub cassettes: CassettesConfig,
     pub api_clients: std::collections::HashMap<String, APIClientConfig>,
 }
 
+fn timezone_default() -> String {
+    "UTC".to_string()
+}
+
+fn concurrency_default() -> usize {
+    1
+}
+
+fn full_sweep_default() -> String {
+    "30d".to_string()
+}
+
 //
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -47,6 +139,73 @@ pub struct APIClientConfig {
     pub limit_requests: usize,
     pub limit_period: String,
     pub limit_status_codes: Vec<u16>,
+    #[serde(default = "concurrency_default")]
+    pub concurrency: usize,
+    // request weights by path, 1 for the others
+    #[serde(default)]
+    pub weights: std::collections::HashMap<String, usize>,
+    // a main account with the exchange env variables, e.g. BINANCE_API_KEY, without any
+    #[serde(default)]
+    pub accounts: Vec<AccountConfig>,
+}
+
+//
+
+/// A named account of an exchange with its own credentials and data, e.g. a master account
+/// and its sub-accounts.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct AccountConfig {
+    pub name: String,
+    pub key: SecretSource,
+    pub secret: SecretSource,
+    #[serde(default)]
+    pub sub_account: bool,
+}
+
+//
+
+/// Where a key or secret is read from, files and command outputs hold it on their first line.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SecretSource {
+    Env {
+        name: String,
+    },
+    // readable by its owner only
+    File {
+        path: String,
+    },
+    Age {
+        path: String,
+        identity: String,
+    },
+    Gpg {
+        path: String,
+    },
+    // e.g. pass show binance/secret
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+//
+
+/// A resolved key or secret, zeroed on drop and never printed.
+#[derive(Clone)]
+pub struct Secret(pub zeroize::Zeroizing<String>);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(REDACTED)")
+    }
 }
 
 //
@@ -54,6 +213,8 @@ pub struct APIClientConfig {
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct HistoryConfig {
     pub do_history: bool,
+    #[serde(default)]
+    pub do_backfill: bool,
     pub interval: String,
     pub spot_only: bool,
     pub quote_only: bool,
@@ -61,6 +222,60 @@ pub struct HistoryConfig {
     pub tradable_only: bool,
     pub fiat_removed: bool,
     pub stablecoins_removed: bool,
+    #[serde(default)]
+    pub delisted: bool,
+    #[serde(default)]
+    pub symbols_delisted: Vec<String>,
+    #[serde(default)]
+    pub mature: MatureConfig,
+    #[serde(default)]
+    pub ranking: RankingConfig,
+}
+
+//
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MatureConfig {
+    pub history_min: String,
+    pub volume_window: String,
+    pub volume_min: f64,
+    pub gap_ratio_max: f64,
+    pub trading_only: bool,
+}
+
+// every pair with klines is mature
+impl Default for MatureConfig {
+    fn default() -> Self {
+        MatureConfig {
+            history_min: "1s".to_string(),
+            volume_window: "30d".to_string(),
+            volume_min: 0.0,
+            gap_ratio_max: 1.0,
+            trading_only: false,
+        }
+    }
+}
+
+//
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RankingConfig {
+    pub window: String,
+    pub top_n_by_volume: Option<usize>,
+    pub min_quote_volume: f64,
+}
+
+// no pair is ranked out
+impl Default for RankingConfig {
+    fn default() -> Self {
+        RankingConfig {
+            window: "30d".to_string(),
+            top_n_by_volume: None,
+            min_quote_volume: 0.0,
+        }
+    }
 }
 
 //
@@ -72,6 +287,12 @@ pub struct TradesConfig {
     pub quotes: Vec<String>,
     pub limit: u64,
     pub recvwindow: u64,
+    #[serde(default)]
+    pub activity_only: bool,
+    #[serde(default)]
+    pub activity_dividends: bool,
+    #[serde(default = "full_sweep_default")]
+    pub full_sweep: String,
 }
 
 //
@@ -88,6 +309,220 @@ pub struct WithdrawalsConfig {
 
 //
 
+/// Binance convert, dust and fiat history, stored next to the trades.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ConversionsConfig {
+    pub do_conversions: Vec<String>,
+    pub recvwindow: u64,
+    pub ts_start: String,
+    pub ts_window: String,
+}
+
+impl Default for ConversionsConfig {
+    fn default() -> Self {
+        ConversionsConfig {
+            do_conversions: Vec::new(),
+            recvwindow: 60000,
+            ts_start: "2023-01-01".to_string(),
+            ts_window: "30d".to_string(),
+        }
+    }
+}
+
+//
+
+/// Binance dividends, earn and staking rewards, stored as an income ledger.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct IncomeConfig {
+    pub do_income: Vec<String>,
+    pub recvwindow: u64,
+    pub ts_start: String,
+    pub ts_window: String,
+}
+
+impl Default for IncomeConfig {
+    fn default() -> Self {
+        IncomeConfig {
+            do_income: Vec::new(),
+            recvwindow: 60000,
+            ts_start: "2023-01-01".to_string(),
+            ts_window: "30d".to_string(),
+        }
+    }
+}
+
+//
+
+/// Binance transfers between own wallets and between master and sub-accounts.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TransfersConfig {
+    pub do_transfers: Vec<String>,
+    // universal transfer types, e.g. MAIN_FUNDING
+    pub types: Vec<String>,
+    // master account transfers to and from sub-accounts, see AccountConfig.sub_account
+    pub sub_accounts: bool,
+    pub recvwindow: u64,
+    pub ts_start: String,
+    pub ts_window: String,
+}
+
+impl Default for TransfersConfig {
+    fn default() -> Self {
+        TransfersConfig {
+            do_transfers: Vec::new(),
+            types: Vec::new(),
+            sub_accounts: false,
+            recvwindow: 60000,
+            ts_start: "2023-01-01".to_string(),
+            ts_window: "30d".to_string(),
+        }
+    }
+}
+
+//
+
+/// Binance cross margin trades, requested with the `binance_margin` client.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MarginConfig {
+    pub do_margin: Vec<String>,
+    // all cross margin pairs of the trades quotes without any
+    pub pairs: Vec<String>,
+    pub limit: u64,
+    pub recvwindow: u64,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        MarginConfig {
+            do_margin: Vec::new(),
+            pairs: Vec::new(),
+            limit: 1000,
+            recvwindow: 60000,
+        }
+    }
+}
+
+//
+
+/// Binance USD-M futures income and trades, requested with the `binance_futures` client.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct FuturesConfig {
+    pub do_futures: Vec<String>,
+    pub limit: u64,
+    pub recvwindow: u64,
+    pub ts_start: String,
+    pub ts_window: String,
+}
+
+impl Default for FuturesConfig {
+    fn default() -> Self {
+        FuturesConfig {
+            do_futures: Vec::new(),
+            limit: 1000,
+            recvwindow: 60000,
+            ts_start: "2023-01-01".to_string(),
+            ts_window: "30d".to_string(),
+        }
+    }
+}
+
+//
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    pub identity_keys: Vec<String>,
+}
+
+//
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub notifiers: Vec<NotifierConfig>,
+    pub rules: Vec<NotificationRule>,
+}
+
+//
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    Webhook {
+        url: String,
+    },
+    Email {
+        host: String,
+        port: u16,
+        starttls: bool,
+        from: String,
+        to: Vec<String>,
+        // names of the env variables holding the SMTP credentials
+        username_env: Option<String>,
+        password_env: Option<String>,
+    },
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+//
+
+/// A change is notified when it matches all given conditions of any rule.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct NotificationRule {
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    #[serde(default)]
+    pub changes: Vec<crate::changes::ChangeKind>,
+    // values the changed entry has to hold, e.g. quoteAsset: USDT
+    #[serde(default)]
+    pub fields: std::collections::HashMap<String, serde_json::Value>,
+}
+
+//
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CassettesConfig {
+    pub mode: CassetteMode,
+}
+
+//
+
+#[derive(
+    Clone, Copy, Debug, Default, Eq, Hash, PartialEq, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    #[default]
+    Off,
+    Record,
+    Replay,
+}
+
+//
+
+/// One recorded request and its response, secrets and signatures are redacted.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Cassette {
+    pub method: String,
+    pub url: String,
+    pub body: String,
+    pub headers: std::collections::BTreeMap<String, String>,
+    pub status: u16,
+    pub response_headers: std::collections::BTreeMap<String, String>,
+    pub response: String,
+}
+
+//
 
 #[derive(Clone, Debug)]
 pub struct DirEntry {
@@ -98,7 +533,95 @@ pub struct DirEntry {
 
 //
 
-#[derive(Default)]
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GapStatus {
+    Fixed,
+    Confirmed,
+}
+
+//
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct KlinesGap {
+    pub ts_start: i64,
+    pub ts_end: i64,
+    pub status: GapStatus,
+    pub recorded_at: i64,
+}
+
+//
+
+/// Activity sweep of the binance trades of an account, `sweep.json` in its trades directory.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct Sweep {
+    /// Time of the last sweep of all pairs.
+    pub ts_full: Option<i64>,
+    /// Time of the last sweep of all cross margin pairs.
+    pub ts_full_margin: Option<i64>,
+    /// Deposits are scanned up to this time, inclusive.
+    pub ts_deposits: Option<i64>,
+    /// Coins deposited up to `ts_deposits`.
+    pub deposit_coins: std::collections::BTreeSet<String>,
+}
+
+//
+
+/// 24 hour rolling statistics of a pair.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct Ticker {
+    pub quote_volume: f64,
+    pub trades: u64,
+}
+
+//
+
+/// Why a pair is or is not considered mature, written to `{exchange}-pairs-maturity.json`.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct PairMaturity {
+    pub pair: String,
+    pub mature: bool,
+    pub ts_first: Option<i64>,
+    pub volume: f64,
+    pub gap_ratio: f64,
+    pub status: Option<String>,
+    pub reasons: Vec<String>,
+}
+
+//
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct SnapshotEntry {
+    pub ts: i64,
+    pub hash: String,
+}
+
+//
+
+/// One symbol across the snapshots of a kind.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct SymbolListing {
+    /// Periods with status `TRADING` from a snapshot on, the last one is open while it lasts.
+    pub periods: Vec<(i64, Option<i64>)>,
+    /// Whether the latest snapshot lists the symbol.
+    pub listed: bool,
+    /// The entry of the last snapshot listing the symbol.
+    pub last: serde_json::Value,
+}
+
+//
+
+/// Listings per symbol kept up to date with the snapshot index of a kind.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct SymbolListings {
+    /// Number of snapshot index entries applied.
+    pub n_entries: usize,
+    pub symbols: std::collections::BTreeMap<String, SymbolListing>,
+}
+
+//
+
+#[derive(Clone, Default)]
 pub struct Pair {
     pub spot: bool,
     pub status: String,
@@ -0,0 +1,118 @@
+use log::{debug, info};
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+//
+//
+//
+
+/// Trading periods `[ts_start, ts_end)` per symbol.
+pub type Universe = BTreeMap<String, Vec<(i64, i64)>>;
+
+//
+
+/// Reconstructs when each symbol of `exchange` was trading.
+///
+/// The symbol listings of archived `pairs-{exchange}` snapshots decide from the first
+/// snapshot on, the coverage of stored klines fills in the time before.
+pub fn universe_get(
+    exchange: &str,
+    interval: &str,
+) -> Result<Universe, Box<dyn std::error::Error>> {
+    let kind = format!("pairs-{exchange}");
+    let index = crate::snapshots::index_read(&kind)?;
+    let ts_now = crate::utc_ms()?;
+    let ts_first = index.first().map(|x| x.ts).unwrap_or(ts_now);
+    let mut universe = Universe::new();
+
+    for (symbol, listing) in crate::snapshots::listings_read(&kind)?.symbols {
+        if !listing.periods.is_empty() {
+            universe.insert(
+                symbol,
+                listing
+                    .periods
+                    .iter()
+                    .map(|x| (x.0, x.1.unwrap_or(ts_now)))
+                    .collect(),
+            );
+        }
+    }
+
+    for dir_entry in crate::paths::dir_list(&crate::paths::dir_klines_interval(exchange, interval))?
+    {
+        if dir_entry.extension != "feather" {
+            continue;
+        }
+        let lf = crate::feather_read(&dir_entry.path)?.filter(col("open").is_not_null());
+        let tss = crate::column_values_i64(lf, "ts")?;
+        let (Some(ts_start), Some(ts_last)) = (tss.iter().min(), tss.iter().max()) else {
+            continue;
+        };
+        let ts_end = crate::interval_add_ms(*ts_last, interval, 1)?.min(ts_first);
+        if *ts_start < ts_end {
+            debug!(
+                "{} klines coverage {} - {}",
+                dir_entry.stem,
+                crate::unix_ms_to_string(*ts_start)?,
+                crate::unix_ms_to_string(ts_end)?
+            );
+            universe
+                .entry(dir_entry.stem)
+                .or_default()
+                .push((*ts_start, ts_end));
+        }
+    }
+
+    // consecutive snapshots of a trading symbol join into one period
+    for periods in universe.values_mut() {
+        periods.sort();
+        let mut merged: Vec<(i64, i64)> = Vec::new();
+        for (ts_start, ts_end) in periods.drain(..) {
+            match merged.last_mut() {
+                Some(x) if x.1 >= ts_start => x.1 = x.1.max(ts_end),
+                _ => merged.push((ts_start, ts_end)),
+            }
+        }
+        *periods = merged;
+    }
+
+    Ok(universe)
+}
+
+//
+
+/// Symbols trading at `ts`.
+pub fn universe_at(universe: &Universe, ts: i64) -> Vec<String> {
+    universe
+        .iter()
+        .filter(|(_, periods)| periods.iter().any(|x| (x.0 <= ts) & (ts < x.1)))
+        .map(|(symbol, _)| symbol.clone())
+        .collect()
+}
+
+//
+
+pub fn universe_write(exchange: &str, interval: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let universe = universe_get(exchange, interval)?;
+    info!("{} universe of {} symbols", exchange, universe.len());
+    crate::json_write(
+        &universe,
+        &crate::paths::dir_config().join(format!("{}-pairs-universe.json", exchange)),
+    )?;
+
+    Ok(())
+}
+
+//
+
+/// The last archived entry of every symbol missing from the latest snapshot of `kind`.
+pub fn entries_delisted(
+    kind: &str,
+) -> Result<BTreeMap<String, serde_json::Value>, Box<dyn std::error::Error>> {
+    Ok(crate::snapshots::listings_read(kind)?
+        .symbols
+        .into_iter()
+        .filter(|(_, v)| !v.listed)
+        .map(|(k, v)| (k, v.last))
+        .collect())
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+//
+
+pub mod mock;
+
+//
+//
+//
+
+// the api crate resolves its directories from the current directory, tests using it run one by one
+static LOCK: Mutex<()> = Mutex::new(());
+
+//
+
+pub struct Workdir {
+    pub path: PathBuf,
+    _guard: MutexGuard<'static, ()>,
+}
+
+//
+
+pub fn workdir(name: &str) -> Workdir {
+    let guard = LOCK.lock().unwrap_or_else(|x| x.into_inner());
+    let path = std::env::temp_dir().join(format!("ant-tests-{}", name));
+    if path.exists() {
+        std::fs::remove_dir_all(&path).expect("remove failed");
+    }
+    std::fs::create_dir_all(&path).expect("create failed");
+    std::env::set_current_dir(&path).expect("current_dir failed");
+
+    Workdir {
+        path,
+        _guard: guard,
+    }
+}
+
+//
+
+/// The repository config with all api clients pointing at `url`.
+pub fn config_get(url: &str) -> api::structs::AppConfig {
+    let mut config: api::structs::AppConfig = api::yaml_read(
+        &PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("config")
+            .join("config.yaml"),
+    )
+    .expect("config failed");
+
+    config.history.interval = "1d".to_string();
+    config.trades.limit = 2;
+    for (name, client) in config.api_clients.iter_mut() {
+        client.url = url.to_string();
+        // kraken nonces are milliseconds and have to increase
+        if name != "kraken" {
+            client.limit_requests = 1000;
+            client.limit_period = "1s".to_string();
+        }
+    }
+
+    config
+}
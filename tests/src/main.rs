@@ -1,9 +1,17 @@
-
-
-
 fn main() {
+    env_logger::init();
 
-}
-
-
+    // serves the fixtures for manual runs, e.g. with api_clients urls set to the printed address
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or("127.0.0.1:8080".to_string());
+    let credentials = tests::mock::Credentials::default();
+    let mock =
+        tests::mock::Mock::start_at(&address, credentials.clone(), None).expect("mock failed");
 
+    println!("mock listening on {}", mock.url);
+    println!("credentials: {:?}", credentials);
+    loop {
+        std::thread::park();
+    }
+}
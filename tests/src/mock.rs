@@ -0,0 +1,1045 @@
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use log::debug;
+use sha2::Digest;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+//
+//
+//
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+//
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub binance_key: String,
+    pub binance_secret: String,
+    // the sub1 sub-account of the binance master account
+    pub binance_sub_key: String,
+    pub binance_sub_secret: String,
+    pub coinmarketcap_key: String,
+    pub kraken_key: String,
+    pub kraken_secret: String,
+}
+impl Default for Credentials {
+    fn default() -> Self {
+        Self {
+            binance_key: "binance-key".to_string(),
+            binance_secret: "binance-secret".to_string(),
+            binance_sub_key: "binance-sub-key".to_string(),
+            binance_sub_secret: "binance-sub-secret".to_string(),
+            coinmarketcap_key: "coinmarketcap-key".to_string(),
+            kraken_key: "kraken-key".to_string(),
+            kraken_secret: general_purpose::STANDARD.encode("kraken-secret"),
+        }
+    }
+}
+impl Credentials {
+    pub fn env_set(&self) {
+        std::env::set_var("BINANCE_API_KEY", &self.binance_key);
+        std::env::set_var("BINANCE_API_SECRET", &self.binance_secret);
+        std::env::set_var("BINANCE_SUB1_API_KEY", &self.binance_sub_key);
+        std::env::set_var("BINANCE_SUB1_API_SECRET", &self.binance_sub_secret);
+        std::env::set_var("COINMARKETCAP_API_KEY", &self.coinmarketcap_key);
+        std::env::set_var("KRAKEN_API_KEY", &self.kraken_key);
+        std::env::set_var("KRAKEN_API_SECRET", &self.kraken_secret);
+    }
+}
+
+//
+
+#[derive(Default)]
+struct State {
+    requests: Vec<(Request, u16)>,
+    statuses: Vec<(String, u16)>,
+    // rows served after the fixture rows of a path
+    rows: HashMap<String, Vec<serde_json::Value>>,
+    throttler: Vec<std::time::Instant>,
+    kraken_nonce: u64,
+}
+
+impl State {
+    fn rows_get(&self, path: &str) -> Vec<serde_json::Value> {
+        self.rows.get(path).cloned().unwrap_or_default()
+    }
+}
+
+//
+
+struct Fixtures {
+    binance_account: String,
+    binance_asset_transfer: Vec<serde_json::Value>,
+    binance_convert: Vec<serde_json::Value>,
+    binance_deposits: Vec<serde_json::Value>,
+    binance_dividends: Vec<serde_json::Value>,
+    binance_dribblet: Vec<serde_json::Value>,
+    binance_earn_flexible: Vec<serde_json::Value>,
+    binance_earn_locked: Vec<serde_json::Value>,
+    binance_eth_staking: Vec<serde_json::Value>,
+    binance_exchangeinfo: String,
+    binance_fiat_payments: Vec<serde_json::Value>,
+    binance_futures_income: Vec<serde_json::Value>,
+    binance_futures_trades: Vec<serde_json::Value>,
+    binance_klines: HashMap<String, Vec<serde_json::Value>>,
+    binance_margin_assets: String,
+    binance_margin_pairs: String,
+    binance_margin_trades: Vec<serde_json::Value>,
+    binance_mytrades: Vec<serde_json::Value>,
+    binance_sub_history: Vec<serde_json::Value>,
+    binance_sub_universal: Vec<serde_json::Value>,
+    binance_ticker24hr: String,
+    binance_withdrawals: Vec<serde_json::Value>,
+    coinmarketcap_fiat: String,
+    coinmarketcap_stablecoins: String,
+    kraken_assetpairs: String,
+    kraken_trades: Vec<(String, serde_json::Value)>,
+    kraken_ledgers: Vec<(String, serde_json::Value)>,
+}
+
+//
+
+pub struct Mock {
+    pub url: String,
+    pub credentials: Credentials,
+    state: Arc<Mutex<State>>,
+}
+
+//
+
+impl Mock {
+    pub fn start(
+        credentials: Credentials,
+        limit: Option<(usize, std::time::Duration)>,
+    ) -> Result<Mock, Box<dyn std::error::Error>> {
+        Self::start_at("127.0.0.1:0", credentials, limit)
+    }
+
+    //
+
+    pub fn start_at(
+        address: &str,
+        credentials: Credentials,
+        limit: Option<(usize, std::time::Duration)>,
+    ) -> Result<Mock, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(address)?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let fixtures = Arc::new(fixtures_read()?);
+        let state = Arc::new(Mutex::new(State::default()));
+
+        {
+            let credentials = credentials.clone();
+            let state = state.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let credentials = credentials.clone();
+                    let fixtures = fixtures.clone();
+                    let state = state.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) =
+                            connection_handle(stream, &credentials, &fixtures, &state, limit)
+                        {
+                            debug!("mock connection failed: {}", e);
+                        }
+                    });
+                }
+            });
+        }
+
+        Ok(Mock {
+            url,
+            credentials,
+            state,
+        })
+    }
+
+    //
+
+    /// The next request to `path` is answered with `status` instead of its fixture.
+    pub fn status_push(&self, path: &str, status: u16) {
+        self.state
+            .lock()
+            .expect("state failed")
+            .statuses
+            .push((path.to_string(), status));
+    }
+
+    //
+
+    /// Rows served by `path` after its fixture rows, later rows replace earlier ones with the
+    /// same id where the endpoint has one.
+    pub fn rows_push(&self, path: &str, rows: Vec<serde_json::Value>) {
+        self.state
+            .lock()
+            .expect("state failed")
+            .rows
+            .entry(path.to_string())
+            .or_default()
+            .extend(rows);
+    }
+
+    //
+
+    /// Requests received so far together with the returned status code.
+    pub fn requests(&self) -> Vec<(Request, u16)> {
+        self.state.lock().expect("state failed").requests.clone()
+    }
+}
+
+//
+//
+//
+
+pub fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+//
+
+fn fixtures_read() -> Result<Fixtures, Box<dyn std::error::Error>> {
+    let read = |name: &str| std::fs::read_to_string(fixtures_dir().join(name));
+
+    // klines are generated from a series description to keep the fixture small
+    let series: HashMap<String, serde_json::Value> =
+        serde_json::from_str(&read("binance-klines.json")?)?;
+    let mut binance_klines = HashMap::new();
+    for (symbol, x) in series.iter() {
+        let ts_start = api::date_to_unix_ms(x["start"].as_str().ok_or("start not found")?)?;
+        let interval = x["interval"].as_str().ok_or("interval not found")?;
+        let missing = x["missing"]
+            .as_array()
+            .ok_or("missing not found")?
+            .iter()
+            .map(|x| api::date_to_unix_ms(x.as_str().ok_or("missing not found")?))
+            .collect::<Result<Vec<i64>, Box<dyn std::error::Error>>>()?;
+        let mut rows = Vec::new();
+        for n in 0..x["count"].as_u64().ok_or("count not found")? as u32 {
+            let ts = api::interval_add_ms(ts_start, interval, n)?;
+            if missing.contains(&ts) {
+                continue;
+            }
+            let price = 100.0 + n as f64;
+            rows.push(serde_json::json!([
+                ts,
+                format!("{:.8}", price),
+                format!("{:.8}", price + 2.0),
+                format!("{:.8}", price - 1.0),
+                format!("{:.8}", price + 1.0),
+                "10.00000000",
+                api::interval_add_ms(ts_start, interval, n + 1)? - 1,
+                format!("{:.8}", 10.0 * price),
+                100 + n,
+                "5.00000000",
+                format!("{:.8}", 5.0 * price),
+                "0"
+            ]));
+        }
+        binance_klines.insert(symbol.clone(), rows);
+    }
+
+    let kraken_map =
+        |name: &str| -> Result<Vec<(String, serde_json::Value)>, Box<dyn std::error::Error>> {
+            let map: HashMap<String, serde_json::Value> = serde_json::from_str(&read(name)?)?;
+            let mut rows = map
+                .into_iter()
+                .collect::<Vec<(String, serde_json::Value)>>();
+            // kraken returns the most recent entries first
+            rows.sort_by(|a, b| {
+                b.1["time"]
+                    .as_f64()
+                    .partial_cmp(&a.1["time"].as_f64())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            Ok(rows)
+        };
+
+    Ok(Fixtures {
+        binance_account: read("binance-account.json")?,
+        binance_asset_transfer: serde_json::from_str(&read("binance-asset-transfer.json")?)?,
+        binance_convert: serde_json::from_str(&read("binance-convert.json")?)?,
+        binance_deposits: serde_json::from_str(&read("binance-deposits.json")?)?,
+        binance_dividends: serde_json::from_str::<serde_json::Value>(&read(
+            "binance-dividends.json",
+        )?)?["rows"]
+            .as_array()
+            .cloned()
+            .ok_or("rows not found")?,
+        binance_dribblet: serde_json::from_str(&read("binance-dribblet.json")?)?,
+        binance_earn_flexible: serde_json::from_str(&read("binance-earn-flexible.json")?)?,
+        binance_earn_locked: serde_json::from_str(&read("binance-earn-locked.json")?)?,
+        binance_eth_staking: serde_json::from_str(&read("binance-eth-staking.json")?)?,
+        binance_exchangeinfo: read("binance-exchangeinfo.json")?,
+        binance_fiat_payments: serde_json::from_str(&read("binance-fiat-payments.json")?)?,
+        binance_futures_income: serde_json::from_str(&read("binance-futures-income.json")?)?,
+        binance_futures_trades: serde_json::from_str(&read("binance-futures-trades.json")?)?,
+        binance_klines,
+        binance_margin_assets: read("binance-margin-assets.json")?,
+        binance_margin_pairs: read("binance-margin-pairs.json")?,
+        binance_margin_trades: serde_json::from_str(&read("binance-margin-trades.json")?)?,
+        binance_mytrades: serde_json::from_str(&read("binance-mytrades.json")?)?,
+        binance_sub_history: serde_json::from_str(&read("binance-sub-history.json")?)?,
+        binance_sub_universal: serde_json::from_str(&read("binance-sub-universal.json")?)?,
+        binance_ticker24hr: read("binance-ticker24hr.json")?,
+        binance_withdrawals: serde_json::from_str(&read("binance-withdrawals.json")?)?,
+        coinmarketcap_fiat: read("coinmarketcap-fiat.json")?,
+        coinmarketcap_stablecoins: read("coinmarketcap-stablecoins.json")?,
+        kraken_assetpairs: read("kraken-assetpairs.json")?,
+        kraken_trades: kraken_map("kraken-trades.json")?,
+        kraken_ledgers: kraken_map("kraken-ledgers.json")?,
+    })
+}
+
+//
+//
+//
+
+fn connection_handle(
+    stream: TcpStream,
+    credentials: &Credentials,
+    fixtures: &Fixtures,
+    state: &Mutex<State>,
+    limit: Option<(usize, std::time::Duration)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or("method not found")?.to_string();
+    let target = parts.next().ok_or("target not found")?.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.insert(k.trim().to_lowercase(), v.trim().to_string());
+        }
+    }
+    let length: usize = headers
+        .get("content-length")
+        .map(|x| x.parse())
+        .transpose()?
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let request = Request {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        headers,
+        body: String::from_utf8(body)?,
+    };
+    let (status, body) = respond(&request, credentials, fixtures, state, limit);
+    debug!("mock {} {} - {}", request.method, request.path, status);
+    state
+        .lock()
+        .map_err(|_| "state failed")?
+        .requests
+        .push((request, status));
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        if status == 200 { "OK" } else { "Error" },
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+//
+
+fn params_get(params: &str) -> HashMap<String, String> {
+    params
+        .split('&')
+        .filter_map(|x| x.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+//
+
+fn respond(
+    request: &Request,
+    credentials: &Credentials,
+    fixtures: &Fixtures,
+    state: &Mutex<State>,
+    limit: Option<(usize, std::time::Duration)>,
+) -> (u16, String) {
+    let mut state = state.lock().expect("state failed");
+
+    if let Some(index) = state.statuses.iter().position(|x| x.0 == request.path) {
+        let (_, status) = state.statuses.remove(index);
+        return (
+            status,
+            r#"{"code":-1003,"msg":"status pushed by the test"}"#.to_string(),
+        );
+    }
+    if let Some((limit_requests, limit_period)) = limit {
+        let now = std::time::Instant::now();
+        state
+            .throttler
+            .retain(|x| now.duration_since(*x) < limit_period);
+        if state.throttler.len() >= limit_requests {
+            return (
+                429,
+                r#"{"code":-1003,"msg":"Too many requests."}"#.to_string(),
+            );
+        }
+        state.throttler.push(now);
+    }
+
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/v3/exchangeInfo") => Ok(fixtures.binance_exchangeinfo.clone()),
+        ("GET", "/api/v3/klines") => binance_klines(request, fixtures),
+        ("GET", "/api/v3/ticker/24hr") => Ok(fixtures.binance_ticker24hr.clone()),
+        ("GET", "/api/v3/myTrades") => {
+            binance_signed(request, credentials).and_then(|x| binance_mytrades(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/margin/allPairs") => Ok(fixtures.binance_margin_pairs.clone()),
+        ("GET", "/sapi/v1/margin/account") => {
+            binance_signed(request, credentials).map(|_| fixtures.binance_margin_assets.clone())
+        }
+        ("GET", "/sapi/v1/margin/myTrades") => {
+            binance_signed(request, credentials).and_then(|x| binance_margin(&x, fixtures))
+        }
+        ("GET", "/fapi/v1/userTrades") => {
+            binance_signed(request, credentials).and_then(|x| binance_fapi_trades(&x, fixtures))
+        }
+        ("GET", "/fapi/v1/income") => {
+            binance_signed(request, credentials).and_then(|x| binance_fapi_income(&x, fixtures))
+        }
+        ("GET", "/api/v3/account") => {
+            binance_signed(request, credentials).map(|_| fixtures.binance_account.clone())
+        }
+        ("GET", "/sapi/v1/capital/deposit/hisrec") => {
+            binance_signed(request, credentials).and_then(|x| binance_deposits(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/asset/assetDividend") => binance_signed(request, credentials)
+            .and_then(|x| binance_dividends(&x, fixtures, &state)),
+        ("GET", "/sapi/v1/simple-earn/flexible/history/rewardsRecord") => {
+            binance_signed(request, credentials)
+                .and_then(|x| binance_earn_flexible(&x, fixtures, &state))
+        }
+        ("GET", "/sapi/v1/simple-earn/locked/history/rewardsRecord") => {
+            binance_signed(request, credentials).and_then(|x| binance_earn_locked(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/eth-staking/eth/history/rewardsHistory") => {
+            binance_signed(request, credentials).and_then(|x| binance_eth_staking(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/convert/tradeFlow") => {
+            binance_signed(request, credentials).and_then(|x| binance_convert(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/asset/dribblet") => binance_signed(request, credentials)
+            .and_then(|x| binance_dribblet(&x, fixtures, &state)),
+        ("GET", "/sapi/v1/fiat/payments") => {
+            binance_signed(request, credentials).and_then(|x| binance_fiat(&x, fixtures, &state))
+        }
+        ("GET", "/sapi/v1/asset/transfer") => {
+            binance_signer(request, credentials).and_then(|x| binance_transfer(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/sub-account/universalTransfer") => {
+            binance_signer(request, credentials).and_then(|x| binance_sub_universal(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/sub-account/transfer/subUserHistory") => {
+            binance_signer(request, credentials).and_then(|x| binance_sub_history(&x, fixtures))
+        }
+        ("GET", "/sapi/v1/capital/withdraw/history") => {
+            binance_signed(request, credentials).and_then(|x| binance_withdrawals(&x, fixtures))
+        }
+        ("GET", "/v1/fiat/map") => {
+            coinmarketcap_checked(request, credentials).map(|_| fixtures.coinmarketcap_fiat.clone())
+        }
+        ("GET", "/v1/cryptocurrency/category") => coinmarketcap_checked(request, credentials)
+            .map(|_| fixtures.coinmarketcap_stablecoins.clone()),
+        // sink for notifications, bodies are kept in the requests log
+        ("POST", "/webhook") => Ok("{}".to_string()),
+        ("GET", "/0/public/AssetPairs") => Ok(fixtures.kraken_assetpairs.clone()),
+        ("POST", "/0/private/TradesHistory") => {
+            kraken_signed(request, credentials, &mut state.kraken_nonce)
+                .map(|x| kraken_page(&x, &fixtures.kraken_trades, "trades"))
+        }
+        ("POST", "/0/private/Ledgers") => {
+            kraken_signed(request, credentials, &mut state.kraken_nonce)
+                .map(|x| kraken_page(&x, &fixtures.kraken_ledgers, "ledger"))
+        }
+        _ => Err((404, r#"{"code":-1,"msg":"not found"}"#.to_string())),
+    };
+    match result {
+        Ok(x) => (200, x),
+        Err(x) => x,
+    }
+}
+
+//
+//
+//
+
+fn binance_signed(
+    request: &Request,
+    credentials: &Credentials,
+) -> Result<HashMap<String, String>, (u16, String)> {
+    binance_signer(request, credentials).map(|x| x.1)
+}
+
+//
+
+// whether the sub-account signed the request and its parameters
+fn binance_signer(
+    request: &Request,
+    credentials: &Credentials,
+) -> Result<(bool, HashMap<String, String>), (u16, String)> {
+    let key = request.headers.get("x-mbx-apikey");
+    let sub = key == Some(&credentials.binance_sub_key);
+    if !sub & (key != Some(&credentials.binance_key)) {
+        return Err((
+            401,
+            r#"{"code":-2015,"msg":"Invalid API-key, IP, or permissions for action."}"#.to_string(),
+        ));
+    }
+    let (payload, signature) = request.query.rsplit_once("&signature=").ok_or((
+        400,
+        r#"{"code":-1102,"msg":"Mandatory parameter 'signature' was not sent."}"#.to_string(),
+    ))?;
+    let secret = match sub {
+        true => &credentials.binance_sub_secret,
+        false => &credentials.binance_secret,
+    };
+    let mut hmac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).expect("hmac failed");
+    hmac.update(payload.as_bytes());
+    if hex::encode(hmac.finalize().into_bytes()) != signature {
+        return Err((
+            400,
+            r#"{"code":-1022,"msg":"Signature for this request is not valid."}"#.to_string(),
+        ));
+    }
+
+    Ok((sub, params_get(payload)))
+}
+
+//
+
+fn binance_klines(request: &Request, fixtures: &Fixtures) -> Result<String, (u16, String)> {
+    let params = params_get(&request.query);
+    let rows = params
+        .get("symbol")
+        .and_then(|x| fixtures.binance_klines.get(x))
+        .ok_or((400, r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string()))?;
+    let limit: usize = params
+        .get("limit")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(500)
+        .min(1000);
+    let bound = |name: &str| params.get(name).and_then(|x| x.parse::<i64>().ok());
+    let (start_time, end_time) = (bound("startTime"), bound("endTime"));
+
+    let rows = rows
+        .iter()
+        .filter(|x| start_time.is_none_or(|ts| x[0].as_i64() >= Some(ts)))
+        .filter(|x| end_time.is_none_or(|ts| x[0].as_i64() <= Some(ts)))
+        .collect::<Vec<&serde_json::Value>>();
+    // without startTime the most recent klines are returned
+    let rows = if start_time.is_some() {
+        rows.into_iter()
+            .take(limit)
+            .collect::<Vec<&serde_json::Value>>()
+    } else {
+        rows[rows.len().saturating_sub(limit)..].to_vec()
+    };
+
+    Ok(serde_json::to_string(&rows).expect("json failed"))
+}
+
+//
+
+fn binance_mytrades(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    Ok(binance_from_id(params, &fixtures.binance_mytrades))
+}
+
+//
+
+fn binance_margin(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    Ok(binance_from_id(params, &fixtures.binance_margin_trades))
+}
+
+//
+
+fn binance_fapi_trades(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    Ok(binance_from_id(params, &fixtures.binance_futures_trades))
+}
+
+//
+
+// trades of the symbol from the fromId one on, oldest first
+fn binance_from_id(params: &HashMap<String, String>, rows: &[serde_json::Value]) -> String {
+    let from_id: u64 = params
+        .get("fromId")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(0);
+    let limit: usize = params
+        .get("limit")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(500);
+    let rows = rows
+        .iter()
+        .filter(|x| x["symbol"].as_str() == params.get("symbol").map(|x| x.as_str()))
+        .filter(|x| x["id"].as_u64() >= Some(from_id))
+        .take(limit)
+        .collect::<Vec<&serde_json::Value>>();
+
+    serde_json::to_string(&rows).expect("json failed")
+}
+
+//
+
+// income oldest first, at most limit records from startTime on
+fn binance_fapi_income(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let limit: usize = params
+        .get("limit")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(100);
+    let page: usize = params.get("page").and_then(|x| x.parse().ok()).unwrap_or(1);
+    let rows = binance_window(params, &fixtures.binance_futures_income, "time")?
+        .into_iter()
+        .skip((page.max(1) - 1) * limit)
+        .take(limit)
+        .collect::<Vec<&serde_json::Value>>();
+
+    Ok(serde_json::to_string(&rows).expect("json failed"))
+}
+
+//
+
+fn binance_withdrawals(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let bound = |name: &str| params.get(name).and_then(|x| x.parse::<i64>().ok());
+    let (start_time, end_time) = (bound("startTime"), bound("endTime"));
+    let rows = fixtures
+        .binance_withdrawals
+        .iter()
+        .filter(|x| {
+            let ts = x["applyTime"]
+                .as_str()
+                .and_then(|x| api::date_to_unix_ms(x).ok());
+            start_time.is_none_or(|t| ts >= Some(t)) & end_time.is_none_or(|t| ts <= Some(t))
+        })
+        .collect::<Vec<&serde_json::Value>>();
+
+    Ok(serde_json::to_string(&rows).expect("json failed"))
+}
+
+//
+
+// rows of `rows` with `field` within the startTime/endTime (or beginTime/endTime) bounds
+fn binance_window<'a>(
+    params: &HashMap<String, String>,
+    rows: &'a [serde_json::Value],
+    field: &str,
+) -> Result<Vec<&'a serde_json::Value>, (u16, String)> {
+    let bound = |name: &str| params.get(name).and_then(|x| x.parse::<i64>().ok());
+    let start_time = bound("startTime").or(bound("beginTime"));
+    let end_time = bound("endTime");
+    if let (Some(a), Some(b)) = (start_time, end_time) {
+        if b - a > 30 * 24 * 3600 * 1000 {
+            return Err((
+                400,
+                r#"{"code":-1127,"msg":"More than 30 days between startTime and endTime."}"#
+                    .to_string(),
+            ));
+        }
+    }
+
+    Ok(rows
+        .iter()
+        .filter(|x| {
+            let ts = x[field].as_i64();
+            start_time.is_none_or(|t| ts >= Some(t)) & end_time.is_none_or(|t| ts <= Some(t))
+        })
+        .collect())
+}
+
+//
+
+fn binance_convert(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let rows = binance_window(params, &fixtures.binance_convert, "createTime")?;
+
+    Ok(serde_json::json!({
+        "list": rows,
+        "startTime": params.get("startTime"),
+        "endTime": params.get("endTime"),
+        "limit": 100,
+        "moreData": false,
+    })
+    .to_string())
+}
+
+//
+
+// the last 100 dribblets of the window
+fn binance_dribblet(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+    state: &State,
+) -> Result<String, (u16, String)> {
+    let mut rows = fixtures.binance_dribblet.clone();
+    rows.extend(state.rows_get("/sapi/v1/asset/dribblet"));
+    let mut rows = binance_window(params, &rows, "operateTime")?;
+    rows.sort_by_key(|x| std::cmp::Reverse(x["operateTime"].as_i64()));
+    let total = rows.len();
+    rows.truncate(100);
+
+    Ok(serde_json::json!({"total": total, "userAssetDribblets": rows}).to_string())
+}
+
+//
+
+// pushed payments update the status of fixture payments with the same orderNo
+fn binance_fiat(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+    state: &State,
+) -> Result<String, (u16, String)> {
+    let transaction_type = params
+        .get("transactionType")
+        .and_then(|x| x.parse::<i64>().ok());
+    let mut payments: Vec<serde_json::Value> = Vec::new();
+    for x in fixtures
+        .binance_fiat_payments
+        .iter()
+        .chain(state.rows_get("/sapi/v1/fiat/payments").iter())
+    {
+        payments.retain(|y| y["orderNo"] != x["orderNo"]);
+        payments.push(x.clone());
+    }
+    let rows = binance_window(params, &payments, "createTime")?
+        .into_iter()
+        .filter(|x| x["transactionType"].as_i64() == transaction_type)
+        .collect::<Vec<&serde_json::Value>>();
+
+    Ok(serde_json::json!({
+        "code": "000000",
+        "message": "success",
+        "data": rows,
+        "total": rows.len(),
+        "success": true,
+    })
+    .to_string())
+}
+
+//
+
+// only the last 90 days are served without startTime, a window spans at most 90 days
+fn binance_deposits(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let param = |name: &str| params.get(name).and_then(|x| x.parse::<i64>().ok());
+    let ts_now = api::utc_ms().unwrap_or_default();
+    let end_time = param("endTime").unwrap_or(ts_now);
+    let start_time = param("startTime").unwrap_or(end_time - 90 * 24 * 3600 * 1000);
+    if end_time - start_time > 90 * 24 * 3600 * 1000 {
+        return Err((
+            400,
+            r#"{"code":-1127,"msg":"More than 90 days between startTime and endTime."}"#
+                .to_string(),
+        ));
+    }
+    let offset = param("offset").unwrap_or(0) as usize;
+    let limit = param("limit").unwrap_or(1000) as usize;
+
+    Ok(serde_json::Value::from(
+        fixtures
+            .binance_deposits
+            .iter()
+            .filter(|x| {
+                let ts = x["insertTime"].as_i64().unwrap_or_default();
+                (start_time <= ts) & (ts <= end_time)
+            })
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect::<Vec<serde_json::Value>>(),
+    )
+    .to_string())
+}
+
+//
+
+fn binance_dividends(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+    state: &State,
+) -> Result<String, (u16, String)> {
+    let limit = params
+        .get("limit")
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(20);
+    let mut rows = fixtures.binance_dividends.clone();
+    rows.extend(state.rows_get("/sapi/v1/asset/assetDividend"));
+    let mut rows = binance_window(params, &rows, "divTime")?;
+    // newest first
+    rows.sort_by_key(|x| std::cmp::Reverse(x["divTime"].as_i64()));
+
+    Ok(serde_json::json!({
+        "rows": rows.iter().take(limit).collect::<Vec<_>>(),
+        "total": rows.len(),
+    })
+    .to_string())
+}
+
+//
+
+// page `current` of `size` rows, the first page is 1
+fn binance_page(params: &HashMap<String, String>, rows: Vec<&serde_json::Value>) -> String {
+    let param = |name: &str, default: usize| {
+        params
+            .get(name)
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(default)
+    };
+    let (current, size) = (param("current", 1).max(1), param("size", 10));
+
+    serde_json::json!({
+        "rows": rows.iter().skip((current - 1) * size).take(size).collect::<Vec<_>>(),
+        "total": rows.len(),
+    })
+    .to_string()
+}
+
+//
+
+fn binance_earn_flexible(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+    state: &State,
+) -> Result<String, (u16, String)> {
+    let mut rows = fixtures.binance_earn_flexible.clone();
+    rows.extend(state.rows_get("/sapi/v1/simple-earn/flexible/history/rewardsRecord"));
+    let rows = binance_window(params, &rows, "time")?
+        .into_iter()
+        .filter(|x| x["type"].as_str() == params.get("type").map(|x| x.as_str()))
+        .collect();
+
+    Ok(binance_page(params, rows))
+}
+
+//
+
+fn binance_earn_locked(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let rows = binance_window(params, &fixtures.binance_earn_locked, "time")?;
+
+    Ok(binance_page(params, rows))
+}
+
+//
+
+fn binance_eth_staking(
+    params: &HashMap<String, String>,
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let rows = binance_window(params, &fixtures.binance_eth_staking, "time")?;
+
+    Ok(binance_page(params, rows))
+}
+
+//
+
+// transfers between the wallets of the master account, the sub-account has none
+fn binance_transfer(
+    signer: &(bool, HashMap<String, String>),
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let (sub, params) = signer;
+    let rows = binance_window(params, &fixtures.binance_asset_transfer, "timestamp")?
+        .into_iter()
+        .filter(|x| !sub & (x["type"].as_str() == params.get("type").map(|x| x.as_str())))
+        .collect();
+
+    Ok(binance_page(params, rows))
+}
+
+//
+
+fn binance_sub_universal(
+    signer: &(bool, HashMap<String, String>),
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let (sub, params) = signer;
+    if *sub {
+        return Err((
+            400,
+            r#"{"code":-12022,"msg":"This account is not a master account."}"#.to_string(),
+        ));
+    }
+    let page = params
+        .get("page")
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(1);
+    let limit = params
+        .get("limit")
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(500);
+    let rows = binance_window(params, &fixtures.binance_sub_universal, "createTimeStamp")?;
+
+    Ok(serde_json::json!({
+        "result": rows.iter().skip((page.max(1) - 1) * limit).take(limit).collect::<Vec<_>>(),
+        "totalCount": rows.len(),
+    })
+    .to_string())
+}
+
+//
+
+fn binance_sub_history(
+    signer: &(bool, HashMap<String, String>),
+    fixtures: &Fixtures,
+) -> Result<String, (u16, String)> {
+    let (sub, params) = signer;
+    if !sub {
+        return Err((
+            400,
+            r#"{"code":-12022,"msg":"This account is not a sub-account."}"#.to_string(),
+        ));
+    }
+    let rows = binance_window(params, &fixtures.binance_sub_history, "time")?;
+
+    Ok(serde_json::to_string(&rows).expect("json failed"))
+}
+
+//
+
+fn coinmarketcap_checked(
+    request: &Request,
+    credentials: &Credentials,
+) -> Result<(), (u16, String)> {
+    if request.headers.get("x-cmc_pro_api_key") != Some(&credentials.coinmarketcap_key) {
+        return Err((
+            401,
+            r#"{"status":{"error_code":1002,"error_message":"API key missing."}}"#.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+//
+
+fn kraken_error(error: &str) -> (u16, String) {
+    // kraken reports errors with a successful status code
+    (200, format!(r#"{{"error":["{}"]}}"#, error))
+}
+
+//
+
+fn kraken_signed(
+    request: &Request,
+    credentials: &Credentials,
+    nonce_last: &mut u64,
+) -> Result<HashMap<String, String>, (u16, String)> {
+    if request.headers.get("api-key") != Some(&credentials.kraken_key) {
+        return Err(kraken_error("EAPI:Invalid key"));
+    }
+    let params = params_get(&request.body);
+    let nonce = params
+        .get("nonce")
+        .ok_or(kraken_error("EAPI:Invalid nonce"))?;
+
+    let secret = general_purpose::STANDARD
+        .decode(&credentials.kraken_secret)
+        .expect("secret failed");
+    let mut hmac = Hmac::<sha2::Sha512>::new_from_slice(&secret).expect("hmac failed");
+    let params_sha2 = {
+        let mut output = sha2::Sha256::default();
+        output.update(nonce);
+        output.update(&request.body);
+        output.finalize()
+    };
+    hmac.update(request.path.as_bytes());
+    hmac.update(&params_sha2);
+    if request.headers.get("api-sign")
+        != Some(&general_purpose::STANDARD.encode(hmac.finalize().into_bytes()))
+    {
+        return Err(kraken_error("EAPI:Invalid signature"));
+    }
+
+    let nonce: u64 = nonce.parse().or(Err(kraken_error("EAPI:Invalid nonce")))?;
+    if nonce <= *nonce_last {
+        return Err(kraken_error("EAPI:Invalid nonce"));
+    }
+    *nonce_last = nonce;
+
+    Ok(params)
+}
+
+//
+
+fn kraken_page(
+    params: &HashMap<String, String>,
+    rows: &[(String, serde_json::Value)],
+    name: &str,
+) -> String {
+    // start is exclusive, end is inclusive, pages hold 50 entries
+    let bound = |name: &str| params.get(name).and_then(|x| x.parse::<f64>().ok());
+    let (start, end) = (bound("start"), bound("end"));
+    let ofs: usize = params.get("ofs").and_then(|x| x.parse().ok()).unwrap_or(0);
+    let rows = rows
+        .iter()
+        .filter(|(_, x)| start.is_none_or(|t| x["time"].as_f64() > Some(t)))
+        .filter(|(_, x)| end.is_none_or(|t| x["time"].as_f64() <= Some(t)))
+        .collect::<Vec<&(String, serde_json::Value)>>();
+    let page = rows
+        .iter()
+        .skip(ofs)
+        .take(50)
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+
+    serde_json::json!({
+        "error": [],
+        "result": {name: page, "count": rows.len()}
+    })
+    .to_string()
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use api::changes::{ChangeKind, ChangesFilter};
+
+//
+//
+//
+
+fn pairs(
+    status: &str,
+    step_size: &str,
+    permissions: &[&str],
+    symbols: &[&str],
+) -> HashMap<String, serde_json::Value> {
+    symbols
+        .iter()
+        .map(|x| {
+            (
+                x.to_string(),
+                serde_json::json!({
+                    "status": status,
+                    "filters": [
+                        {"filterType": "PRICE_FILTER", "tickSize": "0.01"},
+                        {"filterType": "LOT_SIZE", "stepSize": step_size},
+                    ],
+                    "permissions": permissions,
+                }),
+            )
+        })
+        .collect()
+}
+
+//
+
+#[test]
+fn changes_timeline() {
+    let _workdir = tests::workdir("changes-timeline");
+    let config = tests::config_get("http://127.0.0.1:9");
+    let identity_keys = config.diff.identity_keys.clone();
+    let file_pairs = api::paths::file_pairs_binance();
+
+    api::config_write_json(
+        &pairs("TRADING", "0.00001", &["SPOT"], &["BTCUSDT", "LUNAUSDT"]),
+        &file_pairs,
+        &config,
+    )
+    .expect("write failed");
+    let mut data = pairs("BREAK", "0.0001", &["MARGIN", "SPOT"], &["BTCUSDT"]);
+    data.extend(pairs("TRADING", "0.0001", &["SPOT"], &["ETHUSDT"]));
+    api::config_write_json(&data, &file_pairs, &config).expect("write failed");
+
+    // an older stablecoins log
+    let dir_log = api::paths::dir_logs().join("stablecoins");
+    api::paths::dir_create(&dir_log);
+    api::json_write(
+        &serde_json::json!({"new": {"": ["FDUSD"]}, "old": {}, "diff": {}}),
+        &dir_log.join("2023-01-01 00:00:00.json"),
+    )
+    .expect("write failed");
+
+    let changes = api::changes::changes_get(&ChangesFilter::default(), &identity_keys)
+        .expect("changes failed");
+    assert_eq!(changes[0].kind, "stablecoins");
+    assert_eq!(changes[0].change, ChangeKind::Listed);
+    assert_eq!(changes[0].symbol, "FDUSD");
+
+    let changes = api::changes::changes_get(
+        &ChangesFilter {
+            kinds: Vec::from(["pairs-binance".to_string()]),
+            symbol: Some("BTCUSDT".to_string()),
+            ..Default::default()
+        },
+        &identity_keys,
+    )
+    .expect("changes failed");
+    let kinds = changes
+        .iter()
+        .map(|x| (x.path.as_str(), x.change))
+        .collect::<Vec<(&str, ChangeKind)>>();
+    // the reordered permissions list is not a change
+    assert_eq!(
+        kinds,
+        Vec::from([
+            ("/BTCUSDT/filters[LOT_SIZE]/stepSize", ChangeKind::Filter),
+            ("/BTCUSDT/permissions", ChangeKind::Changed),
+            ("/BTCUSDT/status", ChangeKind::Status),
+        ])
+    );
+    assert_eq!(changes[1].new, Vec::from(["\"MARGIN\"".to_string()]));
+    assert!(changes[1].old.is_empty());
+    assert_eq!(changes[2].old, Vec::from(["\"TRADING\"".to_string()]));
+    assert_eq!(changes[2].new, Vec::from(["\"BREAK\"".to_string()]));
+
+    let changes = api::changes::changes_get(
+        &ChangesFilter {
+            ts_from: Some(api::date_to_unix_ms("2024-01-01").expect("date failed")),
+            ..Default::default()
+        },
+        &identity_keys,
+    )
+    .expect("changes failed");
+    assert!(changes.iter().all(|x| x.kind == "pairs-binance"));
+
+    let markdown =
+        api::changes::changes_markdown(&changes, chrono_tz::UTC).expect("markdown failed");
+    assert!(markdown.contains("- new pair: ETHUSDT"));
+    assert!(markdown.contains("- removed pair: LUNAUSDT"));
+    assert!(markdown.contains("- BTCUSDT/status: TRADING → BREAK"));
+    assert!(markdown.contains("- BTCUSDT/filters[LOT_SIZE]/stepSize: 0.00001 → 0.0001"));
+}
+
+//
+
+#[test]
+fn changes_retyped() {
+    let _workdir = tests::workdir("changes-retyped");
+    let config = tests::config_get("http://127.0.0.1:9");
+    let identity_keys = config.diff.identity_keys.clone();
+    let file_pairs = api::paths::file_pairs_binance();
+    let write = |permissions: Option<serde_json::Value>| {
+        let mut data = HashMap::from([(
+            "ETHUSDT".to_string(),
+            serde_json::json!({"status": "TRADING"}),
+        )]);
+        if let Some(x) = permissions {
+            data.insert("BTCUSDT".to_string(), serde_json::json!({"permissions": x}));
+        }
+        api::config_write_json(&data, &file_pairs, &config).expect("write failed");
+        // logs are named by the second
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+    };
+
+    // a scalar turned into an array, then an array change of a pair removed afterwards
+    write(Some(serde_json::json!("SPOT")));
+    write(Some(serde_json::json!(["SPOT", "MARGIN"])));
+    write(Some(serde_json::json!(["SPOT", "MARGIN", "LEVERAGED"])));
+    write(None);
+
+    let changes = api::changes::changes_get(&ChangesFilter::default(), &identity_keys)
+        .expect("changes failed")
+        .into_iter()
+        .filter(|x| x.path == "/BTCUSDT/permissions")
+        .collect::<Vec<api::changes::Change>>();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].old, Vec::from(["\"SPOT\"".to_string()]));
+    assert_eq!(
+        changes[0].new,
+        Vec::from(["[\"SPOT\",\"MARGIN\"]".to_string()])
+    );
+    assert!(changes[1].old.is_empty());
+    assert_eq!(changes[1].new, Vec::from(["\"LEVERAGED\"".to_string()]));
+}
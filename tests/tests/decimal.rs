@@ -0,0 +1,24 @@
+//
+//
+//
+
+#[test]
+fn decimal_exact() {
+    let mul = |a: &str, b: &str| api::decimal_mul(a, b).expect("mul failed");
+    let div = |a: &str, b: &str| api::decimal_div(a, b, 8).expect("div failed");
+
+    assert_eq!(mul("21000.00", "0.00100000"), "21.0000000000");
+    assert_eq!(mul("-1.5", "2"), "-3.0");
+    assert_eq!(mul("3", "4"), "12");
+    // dust amounts keep every digit, f64 would round them
+    assert_eq!(mul("0.000000010000000001", "3"), "0.000000030000000003");
+
+    assert_eq!(div("0.00000123", "0.00000001"), "123.00000000");
+    assert_eq!(div("2", "3"), "0.66666667");
+    assert_eq!(div("-2", "3"), "-0.66666667");
+    assert_eq!(div("0.000000005", "1"), "0.00000001");
+    assert_eq!(div("0.000000004999", "1"), "0.00000000");
+    assert_eq!(div("60", "1200"), "0.05000000");
+    assert!(api::decimal_div("1", "0.000", 8).is_err());
+    assert!(api::decimal_mul("1.x", "2").is_err());
+}
@@ -0,0 +1,112 @@
+use proptest::prelude::*;
+
+//
+//
+//
+
+// small alphabets so that generated values share keys and elements
+fn json_value() -> impl Strategy<Value = serde_json::Value> {
+    let leaf = prop_oneof![
+        Just(serde_json::Value::Null),
+        any::<bool>().prop_map(serde_json::Value::Bool),
+        (-3i64..3).prop_map(|x| serde_json::json!(x)),
+        "[a-c]{0,2}".prop_map(serde_json::Value::String),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(serde_json::Value::Array),
+            prop::collection::btree_map("[a-d]", inner, 0..4)
+                .prop_map(|x| serde_json::Value::Object(x.into_iter().collect())),
+        ]
+    })
+}
+
+//
+
+fn diff_get(
+    left: &serde_json::Value,
+    right: &serde_json::Value,
+    identity_keys: &[String],
+) -> api::diff::Diff {
+    let mut diff = api::diff::Diff::keyed(identity_keys);
+    api::diff::run_json(left, right, "", &mut diff).expect("diff failed");
+    diff
+}
+
+//
+
+// without identity keys arrays are compared as sets, "a" matches elements of generated arrays
+fn identity_keys() -> impl Strategy<Value = Vec<String>> {
+    prop_oneof![Just(Vec::new()), Just(Vec::from(["a".to_string()]))]
+}
+
+//
+//
+//
+
+proptest! {
+    #[test]
+    fn diff_self_empty(x in json_value(), keys in identity_keys()) {
+        let diff = diff_get(&x, &x, &keys);
+        prop_assert!(diff.new.is_empty());
+        prop_assert!(diff.old.is_empty());
+        prop_assert!(diff.diff.is_empty());
+    }
+
+    #[test]
+    fn diff_swap_symmetric(x in json_value(), y in json_value(), keys in identity_keys()) {
+        let diff_xy = diff_get(&x, &y, &keys);
+        let diff_yx = diff_get(&y, &x, &keys);
+
+        // keys new on one side are old on the other
+        prop_assert_eq!(&diff_xy.new, &diff_yx.old);
+        prop_assert_eq!(&diff_xy.old, &diff_yx.new);
+
+        // both array [new, old] and scalar [right, left] pairs swap their sides
+        prop_assert_eq!(diff_xy.diff.len(), diff_yx.diff.len());
+        for (path, [a, b]) in diff_xy.diff.iter() {
+            let swapped = diff_yx.diff.get(path);
+            prop_assert_eq!(swapped, Some(&[b.clone(), a.clone()]));
+        }
+    }
+
+    #[test]
+    fn diff_paths_exist(x in json_value(), y in json_value()) {
+        // every reported path points at a value present on both sides
+        let diff = diff_get(&x, &y, &[]);
+        for path in diff.new.keys().chain(diff.old.keys()).chain(diff.diff.keys()) {
+            prop_assert!(x.pointer(path).is_some() | path.is_empty());
+            prop_assert!(y.pointer(path).is_some() | path.is_empty());
+        }
+    }
+}
+
+//
+
+#[test]
+fn diff_keyed_filters() {
+    let old = serde_json::json!({"BTCUSDT": {"filters": [
+        {"filterType": "PRICE_FILTER", "tickSize": "0.01"},
+        {"filterType": "LOT_SIZE", "stepSize": "0.00001"},
+    ], "permissions": ["SPOT", "MARGIN"]}});
+    let new = serde_json::json!({"BTCUSDT": {"filters": [
+        {"filterType": "LOT_SIZE", "stepSize": "0.0001"},
+        {"filterType": "PRICE_FILTER", "tickSize": "0.01"},
+        {"filterType": "NOTIONAL", "minNotional": "5"},
+    ], "permissions": ["MARGIN", "SPOT"]}});
+    let diff = diff_get(&new, &old, &["filterType".to_string()]);
+
+    assert_eq!(
+        diff.diff["/BTCUSDT/filters[LOT_SIZE]/stepSize"],
+        [
+            Vec::from(["\"0.00001\"".to_string()]),
+            Vec::from(["\"0.0001\"".to_string()])
+        ]
+    );
+    assert_eq!(
+        diff.new["/BTCUSDT/filters"],
+        Vec::from(["NOTIONAL".to_string()])
+    );
+    assert!(diff.old.is_empty());
+    assert_eq!(diff.diff.len(), 1);
+}
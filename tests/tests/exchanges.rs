@@ -0,0 +1,1489 @@
+use polars::prelude::*;
+use std::path::Path;
+
+use tests::mock::{Credentials, Mock};
+
+//
+//
+//
+
+fn height(file_path: &Path) -> usize {
+    api::feather_read(&file_path.to_path_buf())
+        .expect("read failed")
+        .collect()
+        .expect("collect failed")
+        .height()
+}
+
+//
+
+fn requests_count(mock: &Mock, path: &str) -> usize {
+    mock.requests().iter().filter(|x| x.0.path == path).count()
+}
+
+//
+
+fn sync(config: &api::structs::AppConfig) {
+    let mut coinmarketcap = api::api::coinmarketcap::API::new(config).expect("api failed");
+    coinmarketcap.fiat_get().expect("fiat failed");
+    coinmarketcap.stablecoins_get().expect("stablecoins failed");
+
+    let mut binance = api::api::binance::API::new(config).expect("api failed");
+    binance.history_get().expect("history failed");
+    binance.withdrawals_get().expect("withdrawals failed");
+    binance.trades_get().expect("trades failed");
+    binance.conversions_get().expect("conversions failed");
+    binance.income_get().expect("income failed");
+    binance.transfers_get().expect("transfers failed");
+    binance.margin_get().expect("margin failed");
+    binance.futures_get().expect("futures failed");
+
+    let mut kraken = api::api::kraken::API::new(config).expect("api failed");
+    kraken.trades_get().expect("trades failed");
+    kraken.withdrawals_get().expect("withdrawals failed");
+}
+
+//
+//
+//
+
+#[test]
+fn sync_offline() {
+    let workdir = tests::workdir("sync-offline");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+
+    sync(&config);
+
+    let data = workdir.path.join("data");
+    let klines = data.join("klines").join("binance").join("1d");
+    assert_eq!(height(&klines.join("BTCUSDT.feather")), 1200);
+    assert_eq!(height(&klines.join("ETHUSDT.feather")), 300);
+    assert_eq!(height(&klines.join("LUNAUSDT.feather")), 120);
+    // stablecoins are not downloaded
+    assert!(!klines.join("USDCUSDT.feather").exists());
+
+    let nulls = api::feather_read(&klines.join("BTCUSDT.feather"))
+        .expect("read failed")
+        .filter(col("open").is_null())
+        .collect()
+        .expect("collect failed");
+    assert_eq!(nulls.height(), 2);
+
+    let gaps: Vec<api::structs::KlinesGap> = api::json_read(
+        &data
+            .join("gaps")
+            .join("binance")
+            .join("1d")
+            .join("BTCUSDT.json"),
+    )
+    .expect("gaps failed");
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps[0].status, api::structs::GapStatus::Confirmed);
+    assert_eq!(
+        gaps[0].ts_start,
+        api::date_to_unix_ms("2022-06-01").expect("date failed")
+    );
+    assert_eq!(
+        gaps[0].ts_end,
+        api::date_to_unix_ms("2022-06-02").expect("date failed")
+    );
+
+    // trades are paged by fromId with two trades per request
+    let trades = data.join("trades");
+    assert_eq!(
+        height(&trades.join("binance").join("main").join("BTCEUR.feather")),
+        3
+    );
+    assert!(!trades
+        .join("binance")
+        .join("main")
+        .join("ETHEUR.feather")
+        .exists());
+    assert_eq!(
+        height(&trades.join("kraken").join("main").join("trades.feather")),
+        120
+    );
+
+    let withdrawals = data.join("withdrawals");
+    assert_eq!(
+        height(
+            &withdrawals
+                .join("binance")
+                .join("main")
+                .join("withdrawals.feather")
+        ),
+        3
+    );
+    assert_eq!(
+        height(
+            &withdrawals
+                .join("kraken")
+                .join("main")
+                .join("withdrawals.feather")
+        ),
+        2
+    );
+
+    // a second run only adds what is new and does not request confirmed gaps again
+    let n_requests = mock.requests().len();
+    sync(&config);
+    assert_eq!(height(&klines.join("BTCUSDT.feather")), 1200);
+    assert_eq!(
+        height(&trades.join("binance").join("main").join("BTCEUR.feather")),
+        3
+    );
+    assert_eq!(
+        height(&trades.join("kraken").join("main").join("trades.feather")),
+        120
+    );
+    assert!(!mock.requests()[n_requests..]
+        .iter()
+        .any(|x| x.0.path == "/api/v3/klines" && x.0.query.contains("startTime")));
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn credentials_rejected() {
+    let _workdir = tests::workdir("credentials-rejected");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    Credentials {
+        binance_secret: "wrong".to_string(),
+        ..Credentials::default()
+    }
+    .env_set();
+    let config = tests::config_get(&mock.url);
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    assert!(binance.withdrawals_get().is_err());
+    assert!(mock
+        .requests()
+        .iter()
+        .filter(|x| x.0.path == "/sapi/v1/capital/withdraw/history")
+        .all(|x| x.1 == 400));
+}
+
+//
+
+#[test]
+fn status_codes() {
+    let _workdir = tests::workdir("status-codes");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+
+    // rate limit status codes fail without retries
+    mock.status_push("/api/v3/klines", 429);
+    assert!(binance.klines_get("BTCUSDT", Some(10), None, None).is_err());
+    assert_eq!(requests_count(&mock, "/api/v3/klines"), 1);
+
+    // other failures are retried
+    mock.status_push("/api/v3/klines", 500);
+    let df = binance
+        .klines_get("BTCUSDT", Some(10), None, None)
+        .expect("klines failed");
+    assert_eq!(df.height(), 10);
+    assert_eq!(requests_count(&mock, "/api/v3/klines"), 3);
+}
+
+//
+
+#[test]
+fn throttler_limit() {
+    let _workdir = tests::workdir("throttler-limit");
+    let mock = Mock::start(
+        Credentials::default(),
+        Some((5, std::time::Duration::from_secs(1))),
+    )
+    .expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    let client = config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found");
+    client.limit_requests = 5;
+    client.limit_period = "1s".to_string();
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    for _ in 0..8 {
+        binance
+            .klines_get("ETHUSDT", Some(10), None, None)
+            .expect("klines failed");
+    }
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn cassettes_replay() {
+    let workdir = tests::workdir("cassettes-replay");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+
+    config.cassettes.mode = api::structs::CassetteMode::Record;
+    sync(&config);
+    let n_requests = mock.requests().len();
+
+    // recorded requests do not contain keys or signatures
+    for label in ["binance", "binance_margin", "coinmarketcap", "kraken"] {
+        let dir_path = workdir.path.join("cassettes").join(label).join("main");
+        for dir_entry in api::paths::dir_list(&dir_path).expect("list failed") {
+            let cassette = std::fs::read_to_string(&dir_entry.path).expect("read failed");
+            assert!(!cassette.contains(&mock.credentials.binance_key));
+            assert!(!cassette.contains(&mock.credentials.coinmarketcap_key));
+            assert!(!cassette.contains(&mock.credentials.kraken_key));
+        }
+    }
+    let cassette: api::structs::Cassette = api::json_read(
+        &workdir
+            .path
+            .join("cassettes")
+            .join("binance")
+            .join("main")
+            .join("000000.json"),
+    )
+    .expect("cassette failed");
+    assert_eq!(cassette.url, "/api/v3/exchangeInfo");
+    assert_eq!(cassette.headers["x-mbx-apikey"], "REDACTED");
+
+    // replay does not reach the mock
+    std::fs::remove_dir_all(workdir.path.join("data")).expect("remove failed");
+    config.cassettes.mode = api::structs::CassetteMode::Replay;
+    sync(&config);
+    assert_eq!(mock.requests().len(), n_requests);
+
+    let data = workdir.path.join("data");
+    let klines = data.join("klines").join("binance").join("1d");
+    assert_eq!(height(&klines.join("BTCUSDT.feather")), 1200);
+    assert_eq!(
+        height(
+            &data
+                .join("trades")
+                .join("binance")
+                .join("main")
+                .join("BTCEUR.feather")
+        ),
+        3
+    );
+    assert_eq!(
+        height(
+            &data
+                .join("trades")
+                .join("kraken")
+                .join("main")
+                .join("trades.feather")
+        ),
+        120
+    );
+}
+
+//
+
+#[test]
+fn cassettes_accounts() {
+    let workdir = tests::workdir("cassettes-accounts");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found")
+        .accounts
+        .push(api::structs::AccountConfig {
+            name: "sub1".to_string(),
+            key: api::structs::SecretSource::Env {
+                name: "BINANCE_SUB1_API_KEY".to_string(),
+            },
+            secret: api::structs::SecretSource::Env {
+                name: "BINANCE_SUB1_API_SECRET".to_string(),
+            },
+            sub_account: true,
+        });
+    let sync_accounts = |config: &api::structs::AppConfig| {
+        for mut binance in api::api::binance::API::new_accounts(config).expect("api failed") {
+            binance.withdrawals_get().expect("withdrawals failed");
+            binance.trades_get().expect("trades failed");
+            // a second margin client of the account keeps the cassettes of the first
+            binance.margin_get().expect("margin failed");
+            binance.margin_get().expect("margin failed");
+        }
+    };
+    let withdrawals = |account: &str| {
+        height(
+            &workdir
+                .path
+                .join("data")
+                .join("withdrawals")
+                .join("binance")
+                .join(account)
+                .join("withdrawals.feather"),
+        )
+    };
+
+    // the second account does not overwrite the cassettes of the first
+    config.cassettes.mode = api::structs::CassetteMode::Record;
+    sync_accounts(&config);
+    let n_requests = mock.requests().len();
+    let heights = (withdrawals("main"), withdrawals("sub1"));
+    for label in ["binance", "binance_margin"] {
+        for account in ["main", "sub1"] {
+            assert!(workdir
+                .path
+                .join("cassettes")
+                .join(label)
+                .join(account)
+                .join("000000.json")
+                .is_file());
+        }
+    }
+    let n_cassettes = ["main", "sub1"]
+        .iter()
+        .map(|x| {
+            std::fs::read_dir(
+                workdir
+                    .path
+                    .join("cassettes")
+                    .join("binance_margin")
+                    .join(x),
+            )
+            .expect("read failed")
+            .count()
+        })
+        .sum::<usize>();
+    assert_eq!(
+        n_cassettes,
+        mock.requests()
+            .iter()
+            .filter(|x| x.0.path.starts_with("/sapi/v1/margin/"))
+            .count()
+    );
+
+    // and each account replays its own
+    std::fs::remove_dir_all(workdir.path.join("data")).expect("remove failed");
+    config.cassettes.mode = api::structs::CassetteMode::Replay;
+    sync_accounts(&config);
+    assert_eq!(mock.requests().len(), n_requests);
+    assert_eq!((withdrawals("main"), withdrawals("sub1")), heights);
+}
+
+//
+
+#[test]
+fn concurrent_history() {
+    let workdir = tests::workdir("concurrent-history");
+    // the mock limit counts the two coinmarketcap requests too
+    let mock = Mock::start(
+        Credentials::default(),
+        Some((7, std::time::Duration::from_millis(900))),
+    )
+    .expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    for client in config.api_clients.values_mut() {
+        client.limit_requests = 5;
+        client.limit_period = "1s".to_string();
+    }
+
+    let klines = workdir
+        .path
+        .join("data")
+        .join("klines")
+        .join("binance")
+        .join("1d");
+    let frames = |config: &api::structs::AppConfig| {
+        let mut coinmarketcap = api::api::coinmarketcap::API::new(config).expect("api failed");
+        coinmarketcap.fiat_get().expect("fiat failed");
+        coinmarketcap.stablecoins_get().expect("stablecoins failed");
+        let mut binance = api::api::binance::API::new(config).expect("api failed");
+        binance.history_get().expect("history failed");
+        binance.trades_get().expect("trades failed");
+
+        ["BTCUSDT", "ETHUSDT", "LUNAUSDT"]
+            .iter()
+            .map(|pair| {
+                api::feather_read(&klines.join(format!("{pair}.feather")))
+                    .expect("read failed")
+                    .collect()
+                    .expect("collect failed")
+            })
+            .collect::<Vec<DataFrame>>()
+    };
+
+    // workers share the request limit of the client
+    config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found")
+        .concurrency = 4;
+    let concurrent = frames(&config);
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+
+    // a new client does not know the requests of the previous one
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    std::fs::remove_dir_all(workdir.path.join("data")).expect("remove failed");
+    config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found")
+        .concurrency = 1;
+    let sequential = frames(&config);
+    for (a, b) in concurrent.iter().zip(sequential.iter()) {
+        assert!(a.frame_equal_missing(b));
+    }
+}
+
+//
+
+#[test]
+fn klines_backfill() {
+    let workdir = tests::workdir("klines-backfill");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+    sync(&config);
+
+    let data = workdir.path.join("data");
+    let file_path = data
+        .join("klines")
+        .join("binance")
+        .join("1d")
+        .join("BTCUSDT.feather");
+    let file_path_gaps = data
+        .join("gaps")
+        .join("binance")
+        .join("1d")
+        .join("BTCUSDT.json");
+    let ts = |date: &str| api::date_to_unix_ms(date).expect("date failed");
+
+    // a hole in the stored klines and a recorded gap that has been filled since
+    let mut df = api::feather_read(&file_path)
+        .expect("read failed")
+        .filter(
+            col("ts")
+                .lt(lit(ts("2021-03-01")))
+                .or(col("ts").gt(lit(ts("2021-03-03")))),
+        )
+        .collect()
+        .expect("collect failed");
+    api::feather_write(&mut df, &file_path).expect("write failed");
+    let mut gaps: Vec<api::structs::KlinesGap> =
+        api::json_read(&file_path_gaps).expect("gaps failed");
+    gaps.push(api::structs::KlinesGap {
+        ts_start: ts("2021-02-01"),
+        ts_end: ts("2021-02-01"),
+        status: api::structs::GapStatus::Confirmed,
+        recorded_at: 0,
+    });
+    api::json_write(&gaps, &file_path_gaps).expect("write failed");
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.history_get().expect("history failed");
+    assert!(mock.requests().iter().any(|x| {
+        (x.0.path == "/api/v3/klines")
+            & x.0
+                .query
+                .contains(&format!("startTime={}", ts("2021-03-01")))
+            & x.0.query.contains(&format!("endTime={}", ts("2021-03-03")))
+    }));
+    let df = api::feather_read(&file_path)
+        .expect("read failed")
+        .collect()
+        .expect("collect failed");
+    assert_eq!(df.height(), 1200);
+    assert_eq!(df.column("open").expect("open not found").null_count(), 2);
+
+    // only the gap the server cannot fill is kept, and runs do not add it again
+    for _ in 0..2 {
+        let gaps: Vec<api::structs::KlinesGap> =
+            api::json_read(&file_path_gaps).expect("gaps failed");
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].status, api::structs::GapStatus::Confirmed);
+        assert_eq!(gaps[0].ts_start, ts("2022-06-01"));
+        assert_eq!(gaps[0].ts_end, ts("2022-06-02"));
+        binance.history_get().expect("history failed");
+    }
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn history_interval_years() {
+    let _workdir = tests::workdir("history-interval-years");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.history.interval = "1y".to_string();
+
+    // binance has no yearly klines, they are resampled from 1M
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    assert!(binance.history_get().is_err());
+    assert_eq!(requests_count(&mock, "/api/v3/klines"), 0);
+}
+
+//
+
+#[test]
+fn async_client() {
+    let _workdir = tests::workdir("async-client");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+
+    // the client owns its configuration and is shared between tasks
+    let client = std::sync::Arc::new(
+        api::api::client_get("binance", "main", &config, Default::default())
+            .expect("client failed"),
+    );
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime failed");
+    let tasks = ["BTCUSDT", "ETHUSDT", "LUNAUSDT"].map(|pair| {
+        let client = client.clone();
+        runtime.spawn(async move {
+            let url = format!("{}/api/v3/klines?symbol={}&interval=1d", client.url, pair);
+            api::api::request_get_async(&client, api::api::Request::Get(&url))
+                .await
+                .map_err(|e| e.to_string())
+        })
+    });
+    let heights = tasks.map(|x| {
+        let response = runtime
+            .block_on(x)
+            .expect("task failed")
+            .expect("request failed");
+        api::api::binance::klines_deserialize(&response)
+            .expect("deserialize failed")
+            .height()
+    });
+    assert_eq!(heights, [500, 300, 120]);
+
+    // the blocking wrapper shares the throttler with the async requests
+    let url = format!("{}/api/v3/exchangeInfo", client.url);
+    api::api::request_get(&client, api::api::Request::Get(&url)).expect("request failed");
+    assert_eq!(client.throttler.len(), 4);
+    // and can be called from async code too
+    let url = format!("{}/api/v3/klines?symbol=BTCUSDT&interval=1d", client.url);
+    let response = runtime
+        .block_on(async { api::api::request_get(&client, api::api::Request::Get(&url)) })
+        .expect("request failed");
+    assert_eq!(
+        api::api::binance::klines_deserialize(&response)
+            .expect("deserialize failed")
+            .height(),
+        500
+    );
+
+    // clients of the same url share the throttler, requests count with their weight
+    let client_margin = api::api::client_get("binance_margin", "main", &config, Default::default())
+        .expect("client failed");
+    assert_eq!(client_margin.throttler.len(), 5);
+    assert_eq!(client_margin.throttler.weight(), 4 * 2 + 20);
+}
+
+//
+
+#[test]
+fn trades_activity() {
+    let _workdir = tests::workdir("trades-activity");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.trades.full_sweep = "1h".to_string();
+    let file_path_sweep = api::paths::dir_trades()
+        .join("binance")
+        .join("main")
+        .join("sweep.json");
+    let trades_requests = |symbol: &str| {
+        mock.requests()
+            .iter()
+            .filter(|x| {
+                (x.0.path == "/api/v3/myTrades")
+                    & x.0
+                        .query
+                        .split('&')
+                        .any(|x| x == format!("symbol={symbol}"))
+            })
+            .count()
+    };
+
+    // the first run requests every pair
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.trades_get().expect("trades failed");
+    assert_eq!(trades_requests("BTCEUR"), 3);
+    assert_eq!(trades_requests("ETHEUR"), 1);
+    assert_eq!(requests_count(&mock, "/api/v3/account"), 0);
+
+    // afterwards only pairs of held, moved or traded assets
+    binance.trades_get().expect("trades failed");
+    assert_eq!(trades_requests("BTCEUR"), 4);
+    assert_eq!(trades_requests("ETHEUR"), 1);
+    assert_eq!(requests_count(&mock, "/api/v3/account"), 1);
+    // deposits are paged in windows since the withdrawals start
+    let deposits = mock
+        .requests()
+        .into_iter()
+        .filter(|x| x.0.path == "/sapi/v1/capital/deposit/hisrec")
+        .map(|x| x.0.query)
+        .collect::<Vec<String>>();
+    assert!(deposits.len() > 1);
+    assert!(deposits[0].contains(&format!(
+        "startTime={}",
+        api::date_to_unix_ms_tz(
+            &config.withdrawals.ts_start,
+            api::timezone_parse(&config.timezone).unwrap()
+        )
+        .unwrap()
+    )));
+    assert!(deposits.iter().all(|x| x.contains("endTime=")));
+
+    // dividends paid in ETH make ETHEUR active
+    config.trades.activity_dividends = true;
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.trades_get().expect("trades failed");
+    assert_eq!(trades_requests("ETHEUR"), 2);
+    assert_eq!(requests_count(&mock, "/sapi/v1/asset/assetDividend"), 1);
+    // deposits are only requested after the last scan, the coins found before are kept
+    let sweep: api::structs::Sweep = api::json_read(&file_path_sweep).expect("sweep failed");
+    assert_eq!(
+        requests_count(&mock, "/sapi/v1/capital/deposit/hisrec"),
+        deposits.len() + 1
+    );
+    assert!(!sweep.deposit_coins.is_empty());
+    let query = mock
+        .requests()
+        .into_iter()
+        .rev()
+        .filter(|x| x.0.path == "/sapi/v1/capital/deposit/hisrec")
+        .map(|x| x.0.query)
+        .next()
+        .expect("deposits not found");
+    let ts_scanned = query
+        .split('&')
+        .find_map(|x| x.strip_prefix("startTime="))
+        .expect("startTime not found")
+        .parse::<i64>()
+        .expect("parse failed");
+    assert!(ts_scanned > api::utc_ms().expect("utc failed") - 60_000);
+
+    // an expired full sweep requests every pair again
+    config.trades.activity_dividends = false;
+    config.trades.full_sweep = "1s".to_string();
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.trades_get().expect("trades failed");
+    assert_eq!(trades_requests("ETHEUR"), 3);
+    assert_eq!(requests_count(&mock, "/api/v3/account"), 2);
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+
+    // a sweep file with the time of the last full sweep only is read too
+    config.trades.full_sweep = "1h".to_string();
+    let ts_full = api::utc_ms().expect("utc failed");
+    api::json_write(&ts_full, &file_path_sweep).expect("write failed");
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.trades_get().expect("trades failed");
+    assert_eq!(requests_count(&mock, "/api/v3/account"), 3);
+    let sweep: api::structs::Sweep = api::json_read(&file_path_sweep).expect("sweep failed");
+    assert_eq!(sweep.ts_full, Some(ts_full));
+
+    // an invalid full sweep interval is an error, not a skipped sweep
+    config.trades.full_sweep = "1x".to_string();
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    assert!(binance.trades_get().is_err());
+}
+
+//
+
+#[test]
+fn conversions_trades() {
+    let workdir = tests::workdir("conversions-trades");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+    let trades = workdir
+        .path
+        .join("data")
+        .join("trades")
+        .join("binance")
+        .join("main");
+    let column = |source: &str, name: &str| {
+        let df = api::feather_read(&trades.join(format!("{source}.feather")))
+            .expect("read failed")
+            .collect()
+            .expect("collect failed");
+        df.column(name)
+            .expect("column not found")
+            .iter()
+            .map(|x| x.to_string().trim_matches('"').to_string())
+            .collect::<Vec<String>>()
+    };
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.conversions_get().expect("conversions failed");
+
+    // conversions are buys of the target asset paid with the source asset
+    assert_eq!(column("convert", "symbol"), Vec::from(["BTCEUR", "EURETH"]));
+    assert_eq!(
+        column("convert", "qty"),
+        Vec::from(["0.00400000", "800.00000000"])
+    );
+    assert_eq!(
+        column("convert", "quoteqty"),
+        Vec::from(["100.00000000", "0.50000000"])
+    );
+    // dust is bought as BNB, net of the service charge
+    assert_eq!(
+        column("dribblet", "symbol"),
+        Vec::from(["BNBETH", "BNBLUNA"])
+    );
+    assert_eq!(
+        column("dribblet", "price"),
+        Vec::from(["0.05000000", "1200.00000000"])
+    );
+    assert_eq!(
+        column("dribblet", "commissionasset"),
+        Vec::from(["BNB", "BNB"])
+    );
+    // failed fiat payments are left out, sells have the crypto currency as quantity
+    assert_eq!(column("fiat", "symbol"), Vec::from(["BTCEUR", "ETHEUR"]));
+    assert_eq!(column("fiat", "isbuyer"), Vec::from(["true", "false"]));
+    assert_eq!(
+        column("fiat", "qty"),
+        Vec::from(["0.00080000", "0.10000000"])
+    );
+    assert_eq!(column("fiat", "commissionasset"), Vec::from(["EUR", "EUR"]));
+
+    // a second run starts after the last stored trade of each source
+    let n_requests = mock.requests().len();
+    binance.conversions_get().expect("conversions failed");
+    let requests = mock.requests();
+    let first = |path: &str| {
+        requests[n_requests..]
+            .iter()
+            .find(|x| x.0.path == path)
+            .expect("request not found")
+            .0
+            .query
+            .clone()
+    };
+    assert!(first("/sapi/v1/convert/tradeFlow").contains("startTime=1680000000001&"));
+    assert!(first("/sapi/v1/asset/dribblet").contains("startTime=1678000000001&"));
+    assert!(first("/sapi/v1/fiat/payments").contains("beginTime=1681000000001&"));
+    assert_eq!(height(&trades.join("convert.feather")), 2);
+    assert_eq!(height(&trades.join("dribblet.feather")), 2);
+    assert_eq!(height(&trades.join("fiat.feather")), 2);
+    assert!(requests.iter().all(|x| x.1 == 200));
+
+    // more dribblets than one response holds, two share each millisecond
+    mock.rows_push(
+        "/sapi/v1/asset/dribblet",
+        (0..150_i64)
+            .map(|n| {
+                let ts = 1678100000000 + n / 2 * 1000;
+                serde_json::json!({
+                    "operateTime": ts, "totalTransferedAmount": "0.00010000",
+                    "totalServiceChargeAmount": "0.00000200", "transId": 46000000000_i64 + n,
+                    "userAssetDribbletDetails": [{
+                        "transId": 46000000000_i64 + n, "serviceChargeAmount": "0.00000200",
+                        "amount": "10.00000000", "operateTime": ts,
+                        "transferedAmount": "0.00010000", "fromAsset": "DOGE",
+                    }],
+                })
+            })
+            .collect(),
+    );
+    // a payment still processing
+    let payment = |order: &str, ts: i64, status: &str| {
+        serde_json::json!({
+            "orderNo": order, "transactionType": 0, "sourceAmount": "30.00000000",
+            "fiatCurrency": "EUR", "obtainAmount": "0.00100000", "cryptoCurrency": "BTC",
+            "totalFee": "0.30000000", "price": "29700.00000000", "status": status,
+            "paymentMethod": "Credit Card", "createTime": ts, "updateTime": ts,
+        })
+    };
+    mock.rows_push(
+        "/sapi/v1/fiat/payments",
+        Vec::from([
+            payment("pending-1", 1690000000000, "Processing"),
+            payment("pending-2", 1690000001000, "Processing"),
+        ]),
+    );
+    binance.conversions_get().expect("conversions failed");
+    assert_eq!(height(&trades.join("dribblet.feather")), 152);
+    assert_eq!(
+        column("fiat", "status"),
+        Vec::from(["Completed", "Completed", "Processing", "Processing"])
+    );
+
+    // processing payments are requested again and replaced once they are final
+    let n_requests = mock.requests().len();
+    mock.rows_push(
+        "/sapi/v1/fiat/payments",
+        Vec::from([
+            payment("pending-1", 1690000000000, "Completed"),
+            payment("pending-2", 1690000001000, "Failed"),
+        ]),
+    );
+    binance.conversions_get().expect("conversions failed");
+    let requests = mock.requests();
+    assert!(requests[n_requests..]
+        .iter()
+        .find(|x| x.0.path == "/sapi/v1/fiat/payments")
+        .expect("request not found")
+        .0
+        .query
+        .contains("beginTime=1690000000000&"));
+    assert_eq!(
+        column("fiat", "status"),
+        Vec::from(["Completed", "Completed", "Completed"])
+    );
+    assert_eq!(height(&trades.join("dribblet.feather")), 152);
+    assert!(requests.iter().all(|x| x.1 == 200));
+
+    // conversions curated by hand in earlier versions are imported once, times in seconds
+    let file_path_csv = workdir
+        .path
+        .join("data")
+        .join("trades")
+        .join("conversions-binance.csv");
+    std::fs::write(
+        &file_path_csv,
+        "symbol,id,orderid,orderlistid,price,qty,quoteqty,commission,commissionasset,time,\
+         isbuyer,ismaker,isbestmatch,recorded_at\n\
+         ETHEUR,7,7,-1,1500.00000000,0.10000000,150.00000000,0,ETH,1600000000,\
+         true,false,true,1600000100\n",
+    )
+    .expect("write failed");
+    binance.conversions_get().expect("conversions failed");
+    assert!(!file_path_csv.exists());
+    assert!(file_path_csv.with_extension("csv.migrated").is_file());
+    assert_eq!(
+        column("convert", "symbol"),
+        Vec::from(["ETHEUR", "BTCEUR", "EURETH"])
+    );
+    assert_eq!(column("convert", "time")[0], "1600000000000");
+    assert_eq!(column("convert", "price")[0], "1500.00000000");
+    assert_eq!(column("convert", "account")[0], "main");
+    binance.conversions_get().expect("conversions failed");
+    assert_eq!(height(&trades.join("convert.feather")), 3);
+}
+
+//
+
+#[test]
+fn income_ledger() {
+    let workdir = tests::workdir("income-ledger");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+    let file_path = workdir
+        .path
+        .join("data")
+        .join("income")
+        .join("binance")
+        .join("main")
+        .join("income.feather");
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.income_get().expect("income failed");
+
+    let df = api::feather_read(&file_path)
+        .expect("read failed")
+        .collect()
+        .expect("collect failed");
+    let column = |name: &str| {
+        df.column(name)
+            .expect("column not found")
+            .utf8()
+            .expect("utf8 failed")
+            .into_iter()
+            .flatten()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+    };
+    // records of all sources are ordered by time
+    assert_eq!(
+        column("source"),
+        Vec::from([
+            "dividend",
+            "flexible",
+            "flexible",
+            "flexible",
+            "locked",
+            "dividend",
+            "dividend",
+            "eth-staking",
+            "eth-staking",
+        ])
+    );
+    assert_eq!(
+        column("asset"),
+        Vec::from(["ETH", "USDT", "USDT", "USDT", "DOT", "ARB", "ID", "WBETH", "WBETH"])
+    );
+    assert_eq!(
+        column("type")[..6],
+        [
+            "ETH 2.0 Staking",
+            "BONUS",
+            "REALTIME",
+            "REALTIME",
+            "Locked Rewards",
+            "Airdrop"
+        ]
+    );
+    assert_eq!(column("amount")[4], "0.02500000");
+
+    // a second run starts after the last stored record of each source
+    let n_requests = mock.requests().len();
+    binance.income_get().expect("income failed");
+    let requests = mock.requests();
+    let first = |path: &str| {
+        requests[n_requests..]
+            .iter()
+            .find(|x| x.0.path == path)
+            .expect("request not found")
+            .0
+            .query
+            .clone()
+    };
+    assert!(first("/sapi/v1/asset/assetDividend").contains("startTime=1679961600001&"));
+    assert!(first("/sapi/v1/simple-earn/locked/history/rewardsRecord")
+        .contains("startTime=1677628800001&"));
+    assert!(first("/sapi/v1/eth-staking/eth/history/rewardsHistory")
+        .contains("startTime=1685664000001&"));
+    let flexible = |kind: &str| {
+        requests[n_requests..]
+            .iter()
+            .find(|x| {
+                x.0.path == "/sapi/v1/simple-earn/flexible/history/rewardsRecord"
+                    && x.0.query.contains(&format!("type={}&", kind))
+            })
+            .expect("request not found")
+            .0
+            .query
+            .clone()
+    };
+    assert!(flexible("BONUS").contains("startTime=1675296000001&"));
+    assert!(flexible("REALTIME").contains("startTime=1675382400001&"));
+    assert_eq!(height(&file_path), 9);
+    assert!(requests.iter().all(|x| x.1 == 200));
+
+    // a bonus recorded after a later realtime reward is still fetched
+    mock.rows_push(
+        "/sapi/v1/simple-earn/flexible/history/rewardsRecord",
+        Vec::from([serde_json::json!({
+            "asset": "USDT", "rewards": "0.01300000", "projectId": "USDT001",
+            "type": "BONUS", "time": 1675339200000_i64,
+        })]),
+    );
+    binance.income_get().expect("income failed");
+    assert_eq!(height(&file_path), 10);
+
+    // more dividends than one response holds, three share each millisecond
+    mock.rows_push(
+        "/sapi/v1/asset/assetDividend",
+        (0..600_i64)
+            .map(|n| {
+                serde_json::json!({
+                    "id": 1700000000_i64 + n, "amount": "0.10000000", "asset": "BNB",
+                    "divTime": 1690000000000_i64 + n / 3 * 1000, "enInfo": "BNB Vault",
+                    "tranId": 3000000000_i64 + n,
+                })
+            })
+            .collect(),
+    );
+    binance.income_get().expect("income failed");
+    assert_eq!(height(&file_path), 610);
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn transfers_accounts() {
+    let workdir = tests::workdir("transfers-accounts");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.transfers.sub_accounts = true;
+    config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found")
+        .accounts
+        .push(api::structs::AccountConfig {
+            name: "sub1".to_string(),
+            key: api::structs::SecretSource::Env {
+                name: "BINANCE_SUB1_API_KEY".to_string(),
+            },
+            secret: api::structs::SecretSource::Env {
+                name: "BINANCE_SUB1_API_SECRET".to_string(),
+            },
+            sub_account: true,
+        });
+    let dir_path = workdir.path.join("data").join("transfers").join("binance");
+    let column = |name: &str, column: &str| {
+        api::feather_read(&dir_path.join(name).join("transfers.feather"))
+            .expect("read failed")
+            .collect()
+            .expect("collect failed")
+            .column(column)
+            .expect("column not found")
+            .iter()
+            .map(|x| x.to_string().trim_matches('"').to_string())
+            .collect::<Vec<String>>()
+    };
+
+    let accounts = api::api::accounts_get("binance", &config).expect("accounts failed");
+    assert_eq!(accounts.len(), 2);
+    for account in accounts {
+        let mut binance =
+            api::api::binance::API::new_account(&config, account).expect("api failed");
+        binance.transfers_get().expect("transfers failed");
+    }
+
+    // the master account has its wallet transfers and the ones with its sub-accounts
+    assert_eq!(
+        column("main", "source"),
+        Vec::from([
+            "MAIN_FUNDING",
+            "FUNDING_MAIN",
+            "MAIN_UMFUTURE",
+            "sub-account",
+            "sub-account"
+        ])
+    );
+    assert_eq!(column("main", "toaccount")[2], "UMFUTURE");
+    assert_eq!(column("main", "toemail")[3], "sub1@example.com");
+    // the sub-account sees the same transfers from its side
+    assert_eq!(column("sub1", "asset"), Vec::from(["BTC", "USDT"]));
+    assert_eq!(
+        column("sub1", "fromemail"),
+        Vec::from(["master@example.com", ""])
+    );
+    assert_eq!(
+        column("sub1", "toemail"),
+        Vec::from(["", "master@example.com"])
+    );
+    // each account signs its own requests
+    assert_eq!(
+        requests_count(&mock, "/sapi/v1/sub-account/transfer/subUserHistory"),
+        requests_count(&mock, "/sapi/v1/sub-account/universalTransfer")
+    );
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn accounts_storage() {
+    let workdir = tests::workdir("accounts-storage");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found")
+        .accounts
+        .push(api::structs::AccountConfig {
+            name: "sub1".to_string(),
+            key: api::structs::SecretSource::Env {
+                name: "BINANCE_SUB1_API_KEY".to_string(),
+            },
+            secret: api::structs::SecretSource::Env {
+                name: "BINANCE_SUB1_API_SECRET".to_string(),
+            },
+            sub_account: true,
+        });
+    let withdrawals = workdir.path.join("data").join("withdrawals");
+
+    for mut binance in api::api::binance::API::new_accounts(&config).expect("api failed") {
+        binance.withdrawals_get().expect("withdrawals failed");
+    }
+    // pairs are shared by the accounts of an exchange
+    assert_eq!(requests_count(&mock, "/api/v3/exchangeInfo"), 1);
+    // exchanges without configured accounts have a main one
+    let accounts = api::api::accounts_get("kraken", &config).expect("accounts failed");
+    assert_eq!(accounts.len(), 1);
+    assert!(matches!(
+        &accounts[0].key,
+        api::structs::SecretSource::Env { name } if name == "KRAKEN_API_KEY"
+    ));
+
+    // each account has its own rows, labeled with its name
+    for name in ["main", "sub1"] {
+        let df = api::feather_read(
+            &withdrawals
+                .join("binance")
+                .join(name)
+                .join("withdrawals.feather"),
+        )
+        .expect("read failed")
+        .collect()
+        .expect("collect failed");
+        assert_eq!(df.height(), 3);
+        assert!(df
+            .column("account")
+            .expect("column not found")
+            .utf8()
+            .expect("utf8 failed")
+            .into_iter()
+            .all(|x| x == Some(name)));
+    }
+    assert!(!withdrawals
+        .join("binance")
+        .join("withdrawals.feather")
+        .exists());
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn config_baseline() {
+    let workdir = tests::workdir("config-baseline");
+    // config.yaml of the first version, before accounts and the other sections
+    let file_path = workdir.path.join("config.yaml");
+    std::fs::write(
+        &file_path,
+        r#"api_retries: 3
+api_timeout: 5s
+history: # history klines are downloaded from Binance only
+  do_history: true
+  interval: 1w
+  spot_only: true
+  quote_only: true
+  quotes: [USDT]
+  tradable_only: false
+  fiat_removed: true
+  stablecoins_removed: true
+trades:
+  do_trades: [binance, kraken]
+  # following settings are relevant for Binance trades only, kraken downloads trades without settings
+  quote_only: true
+  quotes: [EUR]
+  limit: 1000
+  recvwindow: 60000
+withdrawals:
+  do_withdrawals: [binance, kraken]
+  # following settings are relevant for Binance withdrawals only, kraken downloads withdrawals without settings
+  status: 6
+  limit: 1000
+  recvwindow: 60000
+  ts_start: "2023-01-01"
+  ts_window: 60d
+api_clients:
+  binance:
+    url: https://api.binance.com
+    limit_requests: 1200
+    limit_period: 1m
+    limit_status_codes: [429, 418, 403]
+  coinmarketcap:
+    url: https://pro-api.coinmarketcap.com
+    limit_requests: 30
+    limit_period: 1m
+    limit_status_codes: [429]
+  kraken:
+    url: https://api.kraken.com
+    limit_requests: 1
+    limit_period: 1s
+    limit_status_codes: []
+  "#,
+    )
+    .expect("write failed");
+
+    let config: api::structs::AppConfig = api::yaml_read(&file_path).expect("config failed");
+    assert_eq!(config.timezone, "UTC");
+    assert!(!config.history.do_backfill & !config.history.delisted);
+    assert!(config.history.ranking.top_n_by_volume.is_none());
+    assert!(!config.trades.activity_only);
+    assert!(config.conversions.do_conversions.is_empty());
+    assert!(config.income.do_income.is_empty());
+    assert!(config.transfers.do_transfers.is_empty());
+    assert!(config.margin.do_margin.is_empty());
+    assert!(config.futures.do_futures.is_empty());
+    assert!(config.notifications.notifiers.is_empty());
+    assert_eq!(config.cassettes.mode, api::structs::CassetteMode::Off);
+    assert_eq!(config.api_clients["binance"].concurrency, 1);
+    let accounts = api::api::accounts_get("binance", &config).expect("accounts failed");
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].name, "main");
+}
+
+//
+
+#[test]
+fn accounts_migrate() {
+    let workdir = tests::workdir("accounts-migrate");
+    let config = tests::config_get("http://localhost");
+    let data = workdir.path.join("data");
+    let file_write = |file_path: &Path| {
+        std::fs::create_dir_all(file_path.parent().expect("parent not found"))
+            .expect("create failed");
+        let mut df =
+            df!("id" => [1i64, 2], "time" => [1672531200000i64, 1672617600000]).expect("df failed");
+        api::feather_write(&mut df, &file_path.to_path_buf()).expect("write failed");
+    };
+    let account = |file_path: &Path| {
+        api::feather_read(&file_path.to_path_buf())
+            .expect("read failed")
+            .collect()
+            .expect("collect failed")
+            .column("account")
+            .expect("column not found")
+            .iter()
+            .map(|x| x.to_string().trim_matches('"').to_string())
+            .collect::<Vec<String>>()
+    };
+
+    // data stored before accounts
+    file_write(
+        &data
+            .join("withdrawals")
+            .join("binance")
+            .join("withdrawals.feather"),
+    );
+    file_write(&data.join("trades").join("kraken").join("trades.feather"));
+    // account layouts of this version are left alone, whatever their nesting
+    let file_path_margin = data
+        .join("trades")
+        .join("binance")
+        .join("main")
+        .join("margin")
+        .join("BTCEUR.feather");
+    file_write(&file_path_margin);
+
+    api::accounts_migrate(&config).expect("migrate failed");
+    let file_path = data
+        .join("withdrawals")
+        .join("binance")
+        .join("main")
+        .join("withdrawals.feather");
+    assert_eq!(account(&file_path), Vec::from(["main", "main"]));
+    let file_path = data
+        .join("trades")
+        .join("kraken")
+        .join("main")
+        .join("trades.feather");
+    assert_eq!(account(&file_path), Vec::from(["main", "main"]));
+    assert!(!data
+        .join("trades")
+        .join("kraken")
+        .join("trades.feather")
+        .exists());
+    assert!(api::feather_read(&file_path_margin)
+        .expect("read failed")
+        .schema()
+        .expect("schema failed")
+        .get("account")
+        .is_none());
+
+    // a second run has nothing to move
+    api::accounts_migrate(&config).expect("migrate failed");
+    assert_eq!(height(&file_path), 2);
+
+    // an old file next to an account file is merged into it, records are kept once
+    let file_path_old = data.join("trades").join("kraken").join("trades.feather");
+    let mut df =
+        df!("id" => [2i64, 3], "time" => [1672617600000i64, 1672704000000]).expect("df failed");
+    api::feather_write(&mut df, &file_path_old).expect("write failed");
+    api::accounts_migrate(&config).expect("migrate failed");
+    assert_eq!(height(&file_path), 3);
+    assert_eq!(account(&file_path), Vec::from(["main", "main", "main"]));
+    assert!(!file_path_old.exists());
+
+    // and left in place with columns the account file does not have
+    let mut df =
+        df!("id" => [4i64], "time" => [1672790400000i64], "fee" => ["0.1"]).expect("df failed");
+    api::feather_write(&mut df, &file_path_old).expect("write failed");
+    assert!(api::accounts_migrate(&config).is_err());
+    assert!(file_path_old.exists());
+    assert_eq!(height(&file_path), 3);
+}
+
+//
+
+#[test]
+fn margin_futures() {
+    let workdir = tests::workdir("margin-futures");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.margin.limit = 2;
+    config.futures.limit = 2;
+    let data = workdir.path.join("data");
+    let trades = data.join("trades").join("binance").join("main");
+    let file_path_income = data
+        .join("income")
+        .join("binance")
+        .join("main")
+        .join("futures.feather");
+    let column = |file_path: &Path, name: &str| {
+        api::feather_read(&file_path.to_path_buf())
+            .expect("read failed")
+            .collect()
+            .expect("collect failed")
+            .column(name)
+            .expect("column not found")
+            .iter()
+            .map(|x| x.to_string().trim_matches('"').to_string())
+            .collect::<Vec<String>>()
+    };
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.margin_get().expect("margin failed");
+    binance.futures_get().expect("futures failed");
+
+    // cross margin pairs of the trades quotes, paged by fromId
+    let file_path = trades.join("margin").join("BTCEUR.feather");
+    assert_eq!(column(&file_path, "id"), Vec::from(["101", "102", "103"]));
+    assert_eq!(column(&file_path, "quoteqty")[0], "21.0000000000");
+    assert!(!trades.join("margin").join("ETHEUR.feather").exists());
+    assert_eq!(requests_count(&mock, "/sapi/v1/margin/myTrades"), 3);
+
+    // income sharing a time across pages is stored once
+    assert_eq!(
+        column(&file_path_income, "type"),
+        Vec::from([
+            "TRANSFER",
+            "COMMISSION",
+            "REALIZED_PNL",
+            "COMMISSION",
+            "FUNDING_FEE"
+        ])
+    );
+    assert_eq!(column(&file_path_income, "tradeid")[2], "5002");
+    // trades of the symbols with income
+    let file_path = trades.join("futures").join("BTCUSDT.feather");
+    assert_eq!(
+        column(&file_path, "realizedpnl"),
+        Vec::from(["0", "12.50000000"])
+    );
+    assert_eq!(column(&file_path, "account"), Vec::from(["main", "main"]));
+    assert!(!trades.join("futures").join("ETHUSDT.feather").exists());
+
+    // a second run only asks for newer records, of margin pairs with activity
+    let n_requests = mock.requests().len();
+    binance.margin_get().expect("margin failed");
+    binance.futures_get().expect("futures failed");
+    assert_eq!(height(&file_path_income), 5);
+    assert_eq!(height(&file_path), 2);
+    let requests = mock.requests();
+    assert!(requests[n_requests..]
+        .iter()
+        .filter(|x| x.0.query.contains("symbol=BTCUSDT"))
+        .all(|x| x.0.query.contains("fromId=5003")));
+    let margin_trades = requests[n_requests..]
+        .iter()
+        .filter(|x| x.0.path == "/sapi/v1/margin/myTrades")
+        .collect::<Vec<_>>();
+    assert!(!margin_trades.is_empty());
+    assert!(margin_trades
+        .iter()
+        .all(|x| x.0.query.contains("symbol=BTCEUR")));
+    assert_eq!(requests_count(&mock, "/sapi/v1/margin/account"), 1);
+    assert!(requests.iter().all(|x| x.1 == 200));
+
+    // a key without the permission fails without retries and is told apart
+    mock.status_push("/fapi/v1/income", 401);
+    let n_requests = requests_count(&mock, "/fapi/v1/income");
+    let e = binance.futures_get().expect_err("futures succeeded");
+    assert!(api::api::unauthorized(e.as_ref()));
+    assert_eq!(requests_count(&mock, "/fapi/v1/income"), n_requests + 1);
+}
+
+//
+
+#[test]
+fn futures_income_pages() {
+    let _workdir = tests::workdir("futures-income-pages");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    // a page of one record ends on the time shared by 9003 and 9004
+    config.futures.limit = 1;
+    let file_path = api::paths::dir_income()
+        .join("binance")
+        .join("main")
+        .join("futures.feather");
+
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.futures_get().expect("futures failed");
+
+    let df = api::feather_read(&file_path)
+        .expect("read failed")
+        .collect()
+        .expect("collect failed");
+    assert_eq!(
+        df.column("id")
+            .expect("id not found")
+            .utf8()
+            .expect("utf8 failed")
+            .into_no_null_iter()
+            .collect::<Vec<&str>>(),
+        Vec::from(["9001", "9002", "9003", "9004", "9005"])
+    );
+    assert!(mock
+        .requests()
+        .iter()
+        .filter(|x| x.0.path == "/fapi/v1/income")
+        .any(|x| x.0.query.contains("page=5")));
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
+
+//
+
+#[test]
+fn kraken_trades_offset() {
+    let workdir = tests::workdir("kraken-trades-offset");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let config = tests::config_get(&mock.url);
+    let file_path = workdir
+        .path
+        .join("data")
+        .join("trades")
+        .join("kraken")
+        .join("main")
+        .join("trades.feather");
+    let offsets = |requests: &[(tests::mock::Request, u16)]| {
+        requests
+            .iter()
+            .filter(|x| x.0.path == "/0/private/TradesHistory")
+            .map(|x| {
+                x.0.body
+                    .split('&')
+                    .find_map(|x| x.strip_prefix("ofs="))
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+    };
+
+    // half of the trades share one second, pages of 50 are taken by offset until the count
+    let mut kraken = api::api::kraken::API::new(&config).expect("api failed");
+    kraken.trades_get().expect("trades failed");
+    assert_eq!(height(&file_path), 120);
+    assert_eq!(offsets(&mock.requests()), Vec::from(["0", "50", "100"]));
+
+    // the last stored second is requested again and its trades are stored once
+    let n_requests = mock.requests().len();
+    kraken.trades_get().expect("trades failed");
+    assert_eq!(height(&file_path), 120);
+    assert_eq!(
+        offsets(&mock.requests()[n_requests..]),
+        Vec::from(["0", "50"])
+    );
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
@@ -0,0 +1,161 @@
+use polars::prelude::*;
+
+//
+//
+//
+
+// daily klines from `start`, the day numbers in `missing` are stored as null rows
+fn klines_daily(start: &str, days: i64, missing: &[i64]) -> LazyFrame {
+    let ts_start = api::date_to_unix_ms(start).expect("date failed");
+    let day = api::ti_ms("1d").expect("interval failed");
+    let value = |n: i64, x: f32| (!missing.contains(&n)).then_some(n as f32 + x);
+    DataFrame::new(Vec::from([
+        Series::new(
+            "ts",
+            (0..days).map(|n| ts_start + n * day).collect::<Vec<i64>>(),
+        ),
+        Series::new("open", (0..days).map(|n| value(n, 0.0)).collect::<Vec<_>>()),
+        Series::new("high", (0..days).map(|n| value(n, 1.0)).collect::<Vec<_>>()),
+        Series::new("low", (0..days).map(|n| value(n, -1.0)).collect::<Vec<_>>()),
+        Series::new(
+            "close",
+            (0..days).map(|n| value(n, 0.5)).collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "quote asset volume",
+            (0..days)
+                .map(|n| (!missing.contains(&n)).then_some(10.0_f32))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "number of trades",
+            (0..days)
+                .map(|n| (!missing.contains(&n)).then_some(2_u32))
+                .collect::<Vec<_>>(),
+        ),
+    ]))
+    .expect("df failed")
+    .lazy()
+}
+
+//
+//
+//
+
+#[test]
+fn klines_resample_partial() {
+    // 2023-01-15 to 2023-03-10, 2023-02-01 is a null row
+    let lf = klines_daily("2023-01-15", 55, &[17]);
+    let df = api::klines_resample(lf, "1d", "1M")
+        .expect("resample failed")
+        .collect()
+        .expect("collect failed");
+
+    // the first and the last month are partial buckets starting at the calendar month
+    let ts = |date: &str| api::date_to_unix_ms(date).expect("date failed");
+    let column_i64 =
+        |name: &str| api::column_values_i64(df.clone().lazy(), name).expect("column failed");
+    let column_f32 = |name: &str| {
+        df.column(name)
+            .expect("column not found")
+            .f32()
+            .expect("f32 failed")
+            .into_no_null_iter()
+            .collect::<Vec<f32>>()
+    };
+    assert_eq!(
+        column_i64("ts"),
+        Vec::from([ts("2023-01-01"), ts("2023-02-01"), ts("2023-03-01")])
+    );
+    // the null row does not open february
+    assert_eq!(column_f32("open"), Vec::from([0.0, 18.0, 45.0]));
+    assert_eq!(column_f32("high"), Vec::from([17.0, 45.0, 55.0]));
+    assert_eq!(column_f32("low"), Vec::from([-1.0, 17.0, 44.0]));
+    assert_eq!(column_f32("close"), Vec::from([16.5, 44.5, 54.5]));
+    assert_eq!(
+        column_f32("quote asset volume"),
+        Vec::from([170.0, 270.0, 100.0])
+    );
+    assert_eq!(
+        df.column("number of trades")
+            .expect("column not found")
+            .u32()
+            .expect("u32 failed")
+            .into_no_null_iter()
+            .collect::<Vec<u32>>(),
+        Vec::from([34, 54, 20])
+    );
+
+    // intervals that do not divide into the target are rejected
+    assert!(api::klines_resample(klines_daily("2023-01-15", 5, &[]), "1d", "36h").is_err());
+    assert!(api::klines_resample(klines_daily("2023-01-15", 5, &[]), "1M", "1w").is_err());
+}
+
+//
+
+#[test]
+fn feather_migrate_ms() {
+    let _workdir = tests::workdir("feather-migrate-ms");
+    let ts = api::date_to_unix_ms("2022-06-01").expect("date failed");
+    let file_path_klines = api::paths::dir_klines_interval("binance", "1d").join("BTCUSDT.feather");
+    let mut df = DataFrame::new(Vec::from([
+        Series::new("ts", [(ts / 1000) as u32, (ts / 1000) as u32 + 86_400]),
+        Series::new("open", [Some(1.0_f32), None]),
+    ]))
+    .expect("df failed");
+    api::feather_write(&mut df, &file_path_klines).expect("write failed");
+    let dir_gaps = api::paths::dir_gaps().join("binance").join("1d");
+    std::fs::create_dir_all(&dir_gaps).expect("create failed");
+    let gaps = Vec::from([api::structs::KlinesGap {
+        ts_start: ts / 1000 + 86_400,
+        ts_end: ts / 1000 + 86_400,
+        status: api::structs::GapStatus::Confirmed,
+        recorded_at: ts,
+    }]);
+    api::json_write(&gaps, &dir_gaps.join("BTCUSDT.json")).expect("write failed");
+    let dir_trades = api::paths::dir_trades().join("binance").join("main");
+    std::fs::create_dir_all(&dir_trades).expect("create failed");
+    let mut df = DataFrame::new(Vec::from([
+        Series::new("id", [1_u64]),
+        Series::new("time", [ts as u64]),
+    ]))
+    .expect("df failed");
+    api::feather_write(&mut df, &dir_trades.join("BTCEUR.feather")).expect("write failed");
+
+    // a second run leaves migrated files and gaps alone
+    for _ in 0..2 {
+        api::feather_migrate().expect("migrate failed");
+
+        let df = api::feather_read(&file_path_klines)
+            .expect("read failed")
+            .collect()
+            .expect("collect failed");
+        assert_eq!(
+            df.column("ts").expect("ts not found").dtype(),
+            &DataType::Int64
+        );
+        assert_eq!(
+            api::column_values_i64(df.lazy(), "ts").expect("column failed"),
+            Vec::from([ts, ts + 86_400_000])
+        );
+        let gaps: Vec<api::structs::KlinesGap> =
+            api::json_read(&dir_gaps.join("BTCUSDT.json")).expect("read failed");
+        assert_eq!(
+            (gaps[0].ts_start, gaps[0].ts_end, gaps[0].recorded_at),
+            (ts + 86_400_000, ts + 86_400_000, ts)
+        );
+        // milliseconds stored as u64 keep their value
+        let df = api::feather_read(&dir_trades.join("BTCEUR.feather"))
+            .expect("read failed")
+            .collect()
+            .expect("collect failed");
+        assert_eq!(
+            df.column("time").expect("time not found").dtype(),
+            &DataType::Int64
+        );
+        assert_eq!(
+            api::column_values_i64(df.lazy(), "time").expect("column failed"),
+            Vec::from([ts])
+        );
+    }
+}
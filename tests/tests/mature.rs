@@ -0,0 +1,94 @@
+use polars::prelude::*;
+
+//
+//
+//
+
+fn klines_write(pair: &str, days: i64, volume: f32, missing_every: Option<i64>) {
+    let day = api::ti_ms("1d").expect("interval failed");
+    let ts_today = api::utc_ms().expect("utc failed") / day * day;
+    let tss = (0..days)
+        .map(|n| ts_today - (days - 1 - n) * day)
+        .collect::<Vec<i64>>();
+    let missing = |n: i64| missing_every.is_some_and(|x| n % x == 0);
+    let mut df = DataFrame::new(Vec::from([
+        Series::new("ts", tss),
+        Series::new(
+            "open",
+            (0..days)
+                .map(|n| (!missing(n)).then_some(100.0))
+                .collect::<Vec<Option<f32>>>(),
+        ),
+        Series::new(
+            "quote asset volume",
+            (0..days)
+                .map(|n| (!missing(n)).then_some(volume))
+                .collect::<Vec<Option<f32>>>(),
+        ),
+    ]))
+    .expect("df failed");
+    api::feather_write(
+        &mut df,
+        &api::paths::dir_klines_interval("binance", "1d").join(format!("{pair}.feather")),
+    )
+    .expect("write failed");
+}
+
+//
+//
+//
+
+#[test]
+fn mature_reasons() {
+    let workdir = tests::workdir("mature-reasons");
+    let config = api::structs::MatureConfig {
+        history_min: "7d".to_string(),
+        volume_window: "30d".to_string(),
+        volume_min: 500.0,
+        gap_ratio_max: 0.1,
+        trading_only: true,
+    };
+
+    klines_write("OKUSDT", 60, 100.0, None);
+    klines_write("YOUNGUSDT", 3, 1000.0, None);
+    klines_write("THINUSDT", 60, 1.0, None);
+    klines_write("GAPPYUSDT", 60, 100.0, Some(2));
+    klines_write("HALTEDUSDT", 60, 100.0, None);
+    klines_write("UNKNOWNUSDT", 60, 100.0, None);
+    let status = |x: &str| serde_json::json!({ "status": x });
+    api::json_write(
+        &serde_json::json!({
+            "OKUSDT": status("TRADING"),
+            "YOUNGUSDT": status("TRADING"),
+            "THINUSDT": status("TRADING"),
+            "GAPPYUSDT": status("TRADING"),
+            "HALTEDUSDT": status("BREAK"),
+        }),
+        &api::paths::file_pairs_binance(),
+    )
+    .expect("write failed");
+
+    api::pairs_mature("binance", "1d", &config).expect("mature failed");
+
+    let config_dir = workdir.path.join("config");
+    let mature: Vec<String> =
+        api::json_read(&config_dir.join("binance-pairs-mature.json")).expect("read failed");
+    assert_eq!(mature, Vec::from(["OKUSDT".to_string()]));
+
+    let maturities: Vec<api::structs::PairMaturity> =
+        api::json_read(&config_dir.join("binance-pairs-maturity.json")).expect("read failed");
+    let reasons = |pair: &str| {
+        maturities
+            .iter()
+            .find(|x| x.pair == pair)
+            .expect("pair not found")
+            .reasons
+            .join(", ")
+    };
+    assert_eq!(reasons("OKUSDT"), "");
+    assert!(reasons("YOUNGUSDT").contains("shorter than 7d"));
+    assert!(reasons("THINUSDT").contains("quote volume 30.00 over 30d is below 500"));
+    assert!(reasons("GAPPYUSDT").contains("gap ratio 0.5000 is above 0.1"));
+    assert_eq!(reasons("HALTEDUSDT"), "status is BREAK");
+    assert_eq!(reasons("UNKNOWNUSDT"), "not in current pairs");
+}
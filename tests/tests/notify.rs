@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use api::structs::NotifierConfig;
+use tests::mock::{Credentials, Mock};
+
+//
+//
+//
+
+fn pair(quote: &str, spot: bool, status: &str, permissions: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "quoteAsset": quote,
+        "isSpotTradingAllowed": spot,
+        "status": status,
+        "permissions": permissions,
+    })
+}
+
+//
+
+fn notified(body: &str) -> Vec<(String, String)> {
+    let notification: serde_json::Value = serde_json::from_str(body).expect("json failed");
+    let mut changes = notification["changes"]
+        .as_array()
+        .expect("changes not found")
+        .iter()
+        .map(|x| {
+            (
+                x["symbol"].as_str().unwrap_or_default().to_string(),
+                x["change"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect::<Vec<(String, String)>>();
+    changes.sort();
+    changes
+}
+
+//
+
+#[test]
+fn notify_rules() {
+    let workdir = tests::workdir("notify-rules");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    let mut config = tests::config_get(&mock.url);
+    config.notifications.notifiers = Vec::from([
+        NotifierConfig::Webhook {
+            url: format!("{}/webhook", mock.url),
+        },
+        // a failing notifier does not stop the others
+        NotifierConfig::Command {
+            program: "false".to_string(),
+            args: Vec::new(),
+        },
+        NotifierConfig::Command {
+            program: "sh".to_string(),
+            args: Vec::from(["-c".to_string(), "cat > notified.json".to_string()]),
+        },
+    ]);
+    let file_pairs = api::paths::file_pairs_binance();
+
+    let mut data = HashMap::from([
+        (
+            "BTCUSDT".to_string(),
+            pair("USDT", true, "TRADING", &["SPOT"]),
+        ),
+        (
+            "LUNAUSDT".to_string(),
+            pair("USDT", true, "TRADING", &["SPOT"]),
+        ),
+    ]);
+    api::config_write_json(&data, &file_pairs, &config).expect("write failed");
+
+    data.remove("LUNAUSDT");
+    data.insert(
+        "BTCUSDT".to_string(),
+        pair("USDT", true, "BREAK", &["SPOT", "MARGIN"]),
+    );
+    data.insert(
+        "ETHUSDT".to_string(),
+        pair("USDT", true, "TRADING", &["SPOT"]),
+    );
+    data.insert(
+        "ETHEUR".to_string(),
+        pair("EUR", true, "TRADING", &["SPOT"]),
+    );
+    data.insert("ETHUSDTM".to_string(), pair("USDT", false, "TRADING", &[]));
+    api::config_write_json(&data, &file_pairs, &config).expect("write failed");
+
+    // new USDT spot pairs, delistings and status changes only
+    let expected = Vec::from([
+        ("BTCUSDT".to_string(), "status".to_string()),
+        ("ETHUSDT".to_string(), "listed".to_string()),
+        ("LUNAUSDT".to_string(), "delisted".to_string()),
+    ]);
+    let requests = mock
+        .requests()
+        .into_iter()
+        .filter(|x| x.0.path == "/webhook")
+        .collect::<Vec<(tests::mock::Request, u16)>>();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(notified(&requests[0].0.body), expected);
+    let body = std::fs::read_to_string(workdir.path.join("notified.json")).expect("read failed");
+    assert_eq!(notified(&body), expected);
+
+    // changes without a matching rule are not sent
+    data.insert(
+        "BTCUSDT".to_string(),
+        pair("USDT", true, "BREAK", &["SPOT"]),
+    );
+    api::config_write_json(&data, &file_pairs, &config).expect("write failed");
+    assert_eq!(
+        mock.requests()
+            .iter()
+            .filter(|x| x.0.path == "/webhook")
+            .count(),
+        1
+    );
+}
+
+//
+
+#[test]
+fn notify_in_runtime() {
+    let _workdir = tests::workdir("notify-in-runtime");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    let mut config = tests::config_get(&mock.url);
+    config.notifications.rules = Vec::new();
+    config.notifications.notifiers = Vec::from([NotifierConfig::Webhook {
+        url: format!("{}/webhook", mock.url),
+    }]);
+    let file_pairs = api::paths::file_pairs_binance();
+    let mut data = HashMap::from([(
+        "BTCUSDT".to_string(),
+        pair("USDT", true, "TRADING", &["SPOT"]),
+    )]);
+    api::config_write_json(&data, &file_pairs, &config).expect("write failed");
+
+    // reference data written from async code notifies without blocking the runtime
+    data.insert(
+        "ETHUSDT".to_string(),
+        pair("USDT", true, "TRADING", &["SPOT"]),
+    );
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime failed");
+    runtime
+        .block_on(async { api::config_write_json(&data, &file_pairs, &config) })
+        .expect("write failed");
+    let requests = mock
+        .requests()
+        .into_iter()
+        .filter(|x| x.0.path == "/webhook")
+        .collect::<Vec<(tests::mock::Request, u16)>>();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        notified(&requests[0].0.body),
+        Vec::from([("ETHUSDT".to_string(), "listed".to_string())])
+    );
+}
@@ -0,0 +1,135 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use tests::mock::{Credentials, Mock};
+
+//
+//
+//
+
+#[test]
+fn ranking_top_n() {
+    let workdir = tests::workdir("ranking-top-n");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.history.ranking.top_n_by_volume = Some(2);
+
+    let mut coinmarketcap = api::api::coinmarketcap::API::new(&config).expect("api failed");
+    coinmarketcap.fiat_get().expect("fiat failed");
+    coinmarketcap.stablecoins_get().expect("stablecoins failed");
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.history_get().expect("history failed");
+
+    // only the two pairs with the highest 24 hour quote volume are downloaded
+    let klines = workdir
+        .path
+        .join("data")
+        .join("klines")
+        .join("binance")
+        .join("1d");
+    assert!(klines.join("BTCUSDT.feather").is_file());
+    assert!(klines.join("ETHUSDT.feather").is_file());
+    assert!(!klines.join("LUNAUSDT.feather").exists());
+
+    let df = api::csv_read(
+        &workdir.path.join("config").join("binance-pairs-ranked.csv"),
+        None,
+    )
+    .expect("read failed")
+    .collect()
+    .expect("collect failed");
+    let pairs = df
+        .column("pair")
+        .expect("pair not found")
+        .utf8()
+        .expect("utf8 failed")
+        .into_iter()
+        .flatten()
+        .collect::<Vec<&str>>();
+    assert_eq!(pairs, Vec::from(["BTCUSDT", "ETHUSDT", "LUNAUSDT"]));
+    let selected = df
+        .column("selected")
+        .expect("selected not found")
+        .bool()
+        .expect("bool failed")
+        .into_iter()
+        .flatten()
+        .collect::<Vec<bool>>();
+    assert_eq!(selected, Vec::from([true, true, false]));
+
+    // a minimum volume applies to the tickers, the klines medians stand in without them
+    let ranking = api::structs::RankingConfig {
+        window: "10000d".to_string(),
+        top_n_by_volume: None,
+        min_quote_volume: 3000000.0,
+    };
+    let pairs = Vec::from(["BTCUSDT".to_string(), "ETHUSDT".to_string()]);
+    let tickers = binance.tickers_get().expect("tickers failed");
+    assert_eq!(
+        api::pairs_rank("binance", "1d", &ranking, &pairs, &tickers).expect("rank failed"),
+        Vec::from(["BTCUSDT".to_string()])
+    );
+    let ranking = api::structs::RankingConfig {
+        min_quote_volume: 5000.0,
+        ..ranking
+    };
+    assert_eq!(
+        api::pairs_rank("binance", "1d", &ranking, &pairs, &HashMap::new()).expect("rank failed"),
+        Vec::from(["BTCUSDT".to_string()])
+    );
+}
+
+//
+
+#[test]
+fn ranking_off_weekly() {
+    let workdir = tests::workdir("ranking-off-weekly");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.history.ranking.top_n_by_volume = None;
+    config.history.ranking.min_quote_volume = 0.0;
+
+    let mut coinmarketcap = api::api::coinmarketcap::API::new(&config).expect("api failed");
+    coinmarketcap.fiat_get().expect("fiat failed");
+    coinmarketcap.stablecoins_get().expect("stablecoins failed");
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.history_get().expect("history failed");
+
+    // without a ranking rule the tickers are not requested and no pair is left out
+    assert!(mock
+        .requests()
+        .iter()
+        .all(|x| x.0.path != "/api/v3/ticker/24hr"));
+    assert!(!workdir
+        .path
+        .join("config")
+        .join("binance-pairs-ranked.csv")
+        .exists());
+
+    // weekly medians compare with the 24 hour minimum as a seventh
+    let ts = api::utc_ms().expect("utc failed") - api::ti_ms("1w").expect("interval failed");
+    let mut df = DataFrame::new(Vec::from([
+        Series::new("ts", [ts]),
+        Series::new("quote asset volume", [700.0_f32]),
+        Series::new("number of trades", [14_u32]),
+    ]))
+    .expect("df failed");
+    api::feather_write(
+        &mut df,
+        &api::paths::dir_klines_interval("binance", "1w").join("XUSDT.feather"),
+    )
+    .expect("write failed");
+    let pairs = Vec::from(["XUSDT".to_string()]);
+    let rank = |min_quote_volume: f64| {
+        let ranking = api::structs::RankingConfig {
+            window: "30d".to_string(),
+            top_n_by_volume: None,
+            min_quote_volume,
+        };
+        api::pairs_rank("binance", "1w", &ranking, &pairs, &HashMap::new()).expect("rank failed")
+    };
+    assert_eq!(rank(100.0), pairs);
+    assert!(rank(101.0).is_empty());
+}
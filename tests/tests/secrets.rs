@@ -0,0 +1,94 @@
+use std::os::unix::fs::PermissionsExt;
+
+use api::structs::SecretSource;
+use tests::mock::{Credentials, Mock};
+
+//
+//
+//
+
+fn file_write(file_path: &std::path::Path, text: &str, mode: u32) -> String {
+    std::fs::write(file_path, text).expect("write failed");
+    std::fs::set_permissions(file_path, std::fs::Permissions::from_mode(mode))
+        .expect("permissions failed");
+    file_path.to_string_lossy().to_string()
+}
+
+//
+
+#[test]
+fn secret_sources() {
+    let workdir = tests::workdir("secret-sources");
+
+    std::env::set_var("ANT_TESTS_SECRET", "secret-env");
+    let secret = api::secrets::secret_get(&SecretSource::Env {
+        name: "ANT_TESTS_SECRET".to_string(),
+    })
+    .expect("secret failed");
+    assert_eq!(secret.expose(), "secret-env");
+    // secrets are never printed
+    assert_eq!(format!("{:?}", secret), "Secret(REDACTED)");
+
+    // files hold the secret on their first line and must be private
+    let path = file_write(&workdir.path.join("secret"), "secret-file\n", 0o600);
+    let secret = api::secrets::secret_get(&SecretSource::File { path: path.clone() })
+        .expect("secret failed");
+    assert_eq!(secret.expose(), "secret-file");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))
+        .expect("permissions failed");
+    let e = api::secrets::secret_get(&SecretSource::File { path })
+        .expect_err("readable file accepted")
+        .to_string();
+    assert!(e.contains("644"));
+    assert!(!e.contains("secret-file"));
+
+    let secret = api::secrets::secret_get(&SecretSource::Command {
+        program: "sh".to_string(),
+        args: Vec::from([
+            "-c".to_string(),
+            "printf 'secret-command\\nrest'".to_string(),
+        ]),
+    })
+    .expect("secret failed");
+    assert_eq!(secret.expose(), "secret-command");
+    let e = api::secrets::secret_get(&SecretSource::Command {
+        program: "sh".to_string(),
+        args: Vec::from(["-c".to_string(), "echo secret-failed; exit 1".to_string()]),
+    })
+    .expect_err("failed command accepted")
+    .to_string();
+    assert!(!e.contains("secret-failed"));
+}
+
+//
+
+#[test]
+fn secret_files_signing() {
+    let workdir = tests::workdir("secret-files-signing");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    let mut config = tests::config_get(&mock.url);
+    let account = &mut config
+        .api_clients
+        .get_mut("binance")
+        .expect("binance not found")
+        .accounts[0];
+    account.key = SecretSource::File {
+        path: file_write(
+            &workdir.path.join("key"),
+            &mock.credentials.binance_key,
+            0o600,
+        ),
+    };
+    account.secret = SecretSource::File {
+        path: file_write(
+            &workdir.path.join("secret"),
+            &format!("{}\n", mock.credentials.binance_secret),
+            0o400,
+        ),
+    };
+
+    // signed requests work without the env variables
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.withdrawals_get().expect("withdrawals failed");
+    assert!(mock.requests().iter().all(|x| x.1 == 200));
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+//
+//
+//
+
+#[test]
+fn snapshots_as_of() {
+    let workdir = tests::workdir("snapshots-as-of");
+    let v1 = serde_json::json!({"BTCUSDT": {"filters": [{"filterType": "LOT_SIZE", "stepSize": "0.1"}]}});
+    let v2 = serde_json::json!({"BTCUSDT": {"filters": [{"filterType": "LOT_SIZE", "stepSize": "0.01"}]}});
+
+    api::snapshots::snapshot_write("pairs-test", 1000, &v1).expect("write failed");
+    api::snapshots::snapshot_write("pairs-test", 1500, &v1).expect("write failed");
+    api::snapshots::snapshot_write("pairs-test", 2000, &v2).expect("write failed");
+    api::snapshots::snapshot_write("pairs-test", 3000, &v1).expect("write failed");
+    assert!(api::snapshots::snapshot_write("pairs-test", 2500, &v2).is_err());
+
+    // unchanged data is not indexed again, returning data reuses its object
+    let index = api::snapshots::index_read("pairs-test").expect("index failed");
+    assert_eq!(
+        index.iter().map(|x| x.ts).collect::<Vec<i64>>(),
+        Vec::from([1000, 2000, 3000])
+    );
+    let objects = workdir
+        .path
+        .join("data")
+        .join("snapshots")
+        .join("pairs-test")
+        .join("objects");
+    assert_eq!(std::fs::read_dir(objects).expect("read failed").count(), 2);
+
+    let as_of = |ts| api::snapshots::as_of("pairs-test", ts).expect("as_of failed");
+    assert_eq!(as_of(999), None);
+    assert_eq!(as_of(1000), Some(v1.clone()));
+    assert_eq!(as_of(2999), Some(v2));
+    assert_eq!(as_of(3000), Some(v1));
+    assert_eq!(
+        api::snapshots::as_of("unknown", 3000).expect("as_of failed"),
+        None
+    );
+}
+
+//
+
+#[test]
+fn snapshots_config_write() {
+    let _workdir = tests::workdir("snapshots-config-write");
+    let config = tests::config_get("http://127.0.0.1:9");
+    let file_fiat = api::paths::file_fiat();
+
+    let mut data = HashMap::from([("EUR".to_string(), serde_json::json!({"id": 2790}))]);
+    api::config_write_json(&data, &file_fiat, &config).expect("write failed");
+    let ts = api::utc_ms().expect("utc failed");
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    data.insert("USD".to_string(), serde_json::json!({"id": 2781}));
+    api::config_write_json(&data, &file_fiat, &config).expect("write failed");
+
+    assert_eq!(
+        api::snapshots::as_of("fiat", ts).expect("as_of failed"),
+        Some(serde_json::json!({"EUR": {"id": 2790}}))
+    );
+    assert_eq!(
+        api::snapshots::as_of("fiat", api::utc_ms().expect("utc failed")).expect("as_of failed"),
+        Some(serde_json::to_value(&data).expect("json failed"))
+    );
+}
+
+//
+
+#[test]
+fn snapshots_listings() {
+    let workdir = tests::workdir("snapshots-listings");
+    let pair = |status: &str| serde_json::json!({"status": status});
+    let snapshots = [
+        (
+            1000,
+            serde_json::json!({"AUSDT": pair("TRADING"), "BUSDT": pair("TRADING")}),
+        ),
+        (2000, serde_json::json!({"AUSDT": pair("BREAK")})),
+        (
+            3000,
+            serde_json::json!({"AUSDT": pair("TRADING"), "CUSDT": pair("BREAK")}),
+        ),
+    ];
+    for (ts, data) in snapshots.iter() {
+        api::snapshots::snapshot_write("pairs-binance", *ts, data).expect("write failed");
+    }
+
+    // listings are kept up to date on write, archived objects are not read again
+    std::fs::remove_dir_all(
+        workdir
+            .path
+            .join("data")
+            .join("snapshots")
+            .join("pairs-binance")
+            .join("objects"),
+    )
+    .expect("remove failed");
+    let universe = api::universe::universe_get("binance", "1d").expect("universe failed");
+    assert_eq!(universe["AUSDT"][0], (1000, 2000));
+    assert_eq!(universe["AUSDT"].len(), 2);
+    assert_eq!(universe["AUSDT"][1].0, 3000);
+    assert_eq!(universe["BUSDT"], Vec::from([(1000, 2000)]));
+    assert!(!universe.contains_key("CUSDT"));
+    let delisted = api::universe::entries_delisted("pairs-binance").expect("delisted failed");
+    assert_eq!(
+        delisted.keys().collect::<Vec<&String>>(),
+        Vec::from(["BUSDT"])
+    );
+    assert_eq!(delisted["BUSDT"], pair("TRADING"));
+
+    // listings of archives from before are built from their snapshots once
+    let file_listings = workdir
+        .path
+        .join("data")
+        .join("snapshots")
+        .join("pairs-test")
+        .join("symbols.json");
+    api::snapshots::snapshot_write("pairs-test", 1000, &snapshots[0].1).expect("write failed");
+    std::fs::remove_file(&file_listings).expect("remove failed");
+    let listings = api::snapshots::listings_read("pairs-test").expect("listings failed");
+    assert_eq!(listings.n_entries, 1);
+    assert_eq!(listings.symbols["AUSDT"].periods, Vec::from([(1000, None)]));
+    assert!(file_listings.is_file());
+}
@@ -0,0 +1,78 @@
+//
+//
+//
+
+#[test]
+fn interval_months_years() {
+    assert_eq!(api::interval_parse("1M").expect("parse failed"), (1, 'M'));
+    assert_eq!(api::interval_parse("2y").expect("parse failed"), (2, 'y'));
+    assert_eq!(api::interval_parse("15m").expect("parse failed"), (15, 'm'));
+    for interval in ["", "M", "0M", "1x", "-1d", "1.5h"] {
+        assert!(api::interval_parse(interval).is_err(), "{interval}");
+    }
+    assert_eq!(api::interval_months("3M").expect("months failed"), Some(3));
+    assert_eq!(api::interval_months("2y").expect("months failed"), Some(24));
+    assert_eq!(api::interval_months("4w").expect("months failed"), None);
+    // months and years have no fixed length
+    assert!(api::ti_ms("1M").is_err());
+    assert!(api::ti_ms("1y").is_err());
+
+    let ts = |date: &str| api::date_to_unix_ms(date).expect("date failed");
+    let add = |date: &str, interval: &str, n: u32| {
+        api::interval_add_ms(ts(date), interval, n).expect("add failed")
+    };
+    assert_eq!(add("2023-01-15", "1M", 1), ts("2023-02-15"));
+    assert_eq!(add("2023-11-15", "3M", 1), ts("2024-02-15"));
+    // month ends are clamped to the last day of shorter months
+    assert_eq!(add("2023-01-31", "1M", 1), ts("2023-02-28"));
+    assert_eq!(add("2024-01-31", "1M", 1), ts("2024-02-29"));
+    assert_eq!(add("2024-02-29", "1y", 1), ts("2025-02-28"));
+    assert_eq!(add("2024-02-29", "1y", 4), ts("2028-02-29"));
+    // but only for the month reached, not for every month on the way
+    assert_eq!(add("2023-01-31", "1M", 2), ts("2023-03-31"));
+    assert_eq!(add("2023-01-31", "2M", 2), ts("2023-05-31"));
+    assert_eq!(add("2023-01-01", "1d", 31), ts("2023-02-01"));
+    assert!(api::interval_add_ms(i64::MAX, "1d", 1).is_err());
+}
+
+//
+
+#[test]
+fn dates_dst() {
+    let berlin = api::timezone_parse("Europe/Berlin").expect("timezone failed");
+    let utc = |datetime: &str| api::date_to_unix_ms(datetime).expect("date failed");
+    let local = |datetime: &str| api::date_to_unix_ms_tz(datetime, berlin).expect("date failed");
+    let hour = 3_600_000;
+
+    // dates are local midnight, CET before and CEST after the change on 2023-03-26
+    assert_eq!(local("2023-03-26"), utc("2023-03-25 23:00:00"));
+    assert_eq!(local("2023-03-27"), utc("2023-03-26 22:00:00"));
+    assert_eq!(local("2023-03-26 03:30:00"), utc("2023-03-26 01:30:00"));
+    // the skipped hour does not exist and the repeated one resolves to the earlier instant
+    assert!(api::date_to_unix_ms_tz("2023-03-26 02:30:00", berlin).is_err());
+    assert_eq!(local("2023-10-29 02:30:00"), utc("2023-10-29 00:30:00"));
+    // offsets of RFC3339 strings win over the timezone
+    assert_eq!(
+        local("2023-03-26T12:00:00+00:00"),
+        utc("2023-03-26 12:00:00")
+    );
+
+    let (start, end) =
+        api::day_bounds_ms(utc("2023-03-26 12:00:00"), berlin).expect("bounds failed");
+    assert_eq!((start, end), (local("2023-03-26"), local("2023-03-27")));
+    assert_eq!(end - start, 23 * hour);
+    let (start, end) =
+        api::day_bounds_ms(utc("2023-10-29 12:00:00"), berlin).expect("bounds failed");
+    assert_eq!(start, utc("2023-10-28 22:00:00"));
+    assert_eq!(end, utc("2023-10-29 23:00:00"));
+    assert_eq!(end - start, 25 * hour);
+    // the local day, not the UTC day, of a timestamp past UTC midnight
+    assert_eq!(
+        api::day_bounds_ms(utc("2023-03-26 22:30:00"), berlin).expect("bounds failed"),
+        (local("2023-03-27"), local("2023-03-28"))
+    );
+    assert_eq!(
+        api::day_bounds_ms(end - 1, berlin).expect("bounds failed"),
+        (start, end)
+    );
+}
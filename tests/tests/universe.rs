@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use tests::mock::{Credentials, Mock};
+
+//
+//
+//
+
+fn klines_requests(mock: &Mock, symbol: &str) -> usize {
+    mock.requests()
+        .iter()
+        .filter(|x| {
+            (x.0.path == "/api/v3/klines")
+                & x.0
+                    .query
+                    .split('&')
+                    .any(|x| x == format!("symbol={symbol}"))
+        })
+        .count()
+}
+
+//
+//
+//
+
+#[test]
+fn universe_delisted() {
+    let workdir = tests::workdir("universe-delisted");
+    let mock = Mock::start(Credentials::default(), None).expect("mock failed");
+    mock.credentials.env_set();
+    let mut config = tests::config_get(&mock.url);
+    config.history.delisted = true;
+    config.history.symbols_delisted = Vec::from(["GONEUSDT".to_string()]);
+
+    // OLDUSDT was listed when an earlier run archived the pairs
+    let exchange_info: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(tests::mock::fixtures_dir().join("binance-exchangeinfo.json"))
+            .expect("read failed"),
+    )
+    .expect("parse failed");
+    let mut pairs = exchange_info["symbols"]
+        .as_array()
+        .expect("symbols not found")
+        .iter()
+        .map(|x| {
+            (
+                x["symbol"].as_str().expect("symbol not found").to_string(),
+                x.clone(),
+            )
+        })
+        .collect::<BTreeMap<String, serde_json::Value>>();
+    let mut old = pairs["BTCUSDT"].clone();
+    old["symbol"] = serde_json::json!("OLDUSDT");
+    old["baseAsset"] = serde_json::json!("OLD");
+    pairs.insert("OLDUSDT".to_string(), old);
+    let ts_archived = api::date_to_unix_ms("2023-06-01").expect("date failed");
+    api::snapshots::snapshot_write(
+        "pairs-binance",
+        ts_archived,
+        &serde_json::to_value(&pairs).expect("value failed"),
+    )
+    .expect("snapshot failed");
+
+    let mut coinmarketcap = api::api::coinmarketcap::API::new(&config).expect("api failed");
+    coinmarketcap.fiat_get().expect("fiat failed");
+    coinmarketcap.stablecoins_get().expect("stablecoins failed");
+    let mut binance = api::api::binance::API::new(&config).expect("api failed");
+    binance.history_get().expect("history failed");
+
+    // delisted symbols are downloaded, unknown ones do not stop the history
+    let klines = workdir
+        .path
+        .join("data")
+        .join("klines")
+        .join("binance")
+        .join("1d");
+    let height = |pair: &str| {
+        api::feather_read(&klines.join(format!("{pair}.feather")))
+            .expect("read failed")
+            .collect()
+            .expect("collect failed")
+            .height()
+    };
+    assert_eq!(height("OLDUSDT"), 200);
+    assert_eq!(height("BTCUSDT"), 1200);
+    assert!(!klines.join("GONEUSDT.feather").exists());
+
+    // kline coverage decides before the first snapshot, snapshots after
+    let universe = api::universe::universe_get("binance", "1d").expect("universe failed");
+    let at = |date: &str| {
+        api::universe::universe_at(&universe, api::date_to_unix_ms(date).expect("date failed"))
+    };
+    assert_eq!(at("2020-03-01"), Vec::from(["OLDUSDT".to_string()]));
+    assert_eq!(
+        at("2022-03-01"),
+        Vec::from(["BTCUSDT".to_string(), "LUNAUSDT".to_string()])
+    );
+    assert!(at("2023-07-01").contains(&"OLDUSDT".to_string()));
+    assert!(!at("2023-07-01").contains(&"LUNAUSDT".to_string()));
+    let ts_latest = api::snapshots::index_read("pairs-binance")
+        .expect("index failed")
+        .last()
+        .expect("snapshot not found")
+        .ts;
+    let now = api::universe::universe_at(&universe, ts_latest);
+    assert!(!now.contains(&"OLDUSDT".to_string()));
+    assert!(now.contains(&"ETHUSDT".to_string()));
+    assert!(workdir
+        .path
+        .join("config")
+        .join("binance-pairs-universe.json")
+        .is_file());
+
+    // a complete delisted history is not requested again
+    let n_old = klines_requests(&mock, "OLDUSDT");
+    binance.history_get().expect("history failed");
+    assert_eq!(klines_requests(&mock, "OLDUSDT"), n_old);
+}